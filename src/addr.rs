@@ -1,18 +1,58 @@
 //! Address type and helper structures to manipulate and introspect it
 //!
-use std::collections::HashSet;
+use std::cmp::max;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::iter::FromIterator;
 use std::net::{IpAddr, SocketAddr, AddrParseError};
+use std::num::ParseIntError;
+use std::str::FromStr;
 use std::slice::Iter as VecIter;
 
 use rand::{thread_rng, Rng};
+#[cfg(feature="serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
 
 /// A type alias for a weight for each name in an address
 ///
 /// (don't rely on actual type, it's likely to change in near future)
 pub type Weight = u64;
 
+/// Sum of the weights of each source's set after `WeightMode::Normalize`
+const NORMALIZED_TOTAL: Weight = 1_000_000;
+
+quick_error! {
+    /// Error parsing `Address` from string
+    #[derive(Debug)]
+    pub enum ParseError {
+        /// Set of addresses is not enclosed in brackets
+        Brackets(set: String) {
+            description("set of addresses must be enclosed in brackets")
+            display("set of addresses must be enclosed in brackets: {:?}",
+                    set)
+        }
+        /// Entry has unexpected parts (only address and weight allowed)
+        InvalidEntry(entry: String) {
+            description("invalid address entry")
+            display("invalid address entry: {:?}", entry)
+        }
+        /// Socket address can't be parsed
+        InvalidAddr(err: AddrParseError) {
+            description("invalid socket address")
+            display("invalid socket address: {}", err)
+            from()
+        }
+        /// Weight is not a number
+        InvalidWeight(err: ParseIntError) {
+            description("invalid weight")
+            display("invalid weight: {}", err)
+            from()
+        }
+    }
+}
+
 /// Address that nameservice has returned
 ///
 /// We hide this structure to allow future additions. There is `Builder`
@@ -21,6 +61,16 @@ pub type Weight = u64;
 ///
 /// Internally it's an `Arc` over a structure so it's cheap to clone and you
 /// can cache addresses.
+///
+/// Address is normalized on construction: every set of the same priority
+/// is sorted by `SocketAddr`, so equality, hashing and comparison are cheap
+/// and don't depend on the order in which the addresses were added.
+///
+/// Duplicates are merged on construction too. If the same `SocketAddr` is
+/// added multiple times with the same priority, the entries are replaced by
+/// a single one having the sum of their weights, so the probability to pick
+/// the address doesn't change. If it's added with different priorities,
+/// only the highest priority is kept. Empty sets are skipped.
 #[derive(Clone, Debug)]
 pub struct Address(Arc<Internal>);
 
@@ -46,6 +96,21 @@ pub struct Builder {
     addresses: Vec<Vec<(Weight, SocketAddr)>>,
 }
 
+/// Defines how weights are combined by `merge`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightMode {
+    /// Weights of the same address in different sources are summed
+    Sum,
+    /// The largest weight of the address among sources is used
+    Max,
+    /// Weights of each source are scaled so that every set of the same
+    /// priority sums up to the same value, then weights are summed
+    ///
+    /// This means every source gets an equal share of the traffic
+    /// regardless of the number of addresses or absolute weights it has.
+    Normalize,
+}
+
 /// A structure that represents a set of addresses of the same priority
 #[derive(Debug)]
 pub struct WeightedSet<'a> {
@@ -99,27 +164,21 @@ impl<'a> Iterator for AddressIter<'a> {
 
 impl From<(IpAddr, u16)> for Address {
     fn from((ip, port): (IpAddr, u16)) -> Address {
-        Address(Arc::new(Internal {
-            addresses: vec![vec![(0, SocketAddr::new(ip, port))]],
-        }))
+        Address::from_sets(vec![vec![(0, SocketAddr::new(ip, port))]])
     }
 }
 
 impl From<SocketAddr> for Address {
     fn from(addr: SocketAddr) -> Address {
-        Address(Arc::new(Internal {
-            addresses: vec![vec![(0, addr)]],
-        }))
+        Address::from_sets(vec![vec![(0, addr)]])
     }
 }
 
 impl<'a> From<&'a [SocketAddr]> for Address {
     fn from(addr: &[SocketAddr]) -> Address {
-        Address(Arc::new(Internal {
-            addresses: vec![
-                addr.iter().map(|&a| (0, a)).collect()
-            ],
-        }))
+        Address::from_sets(vec![
+            addr.iter().map(|&a| (0, a)).collect()
+        ])
     }
 }
 
@@ -127,9 +186,7 @@ impl FromIterator<SocketAddr> for Address {
     fn from_iter<T>(iter: T) -> Self
         where T: IntoIterator<Item=SocketAddr>
     {
-        Address(Arc::new(Internal {
-            addresses: vec![iter.into_iter().map(|a| (0, a)).collect()],
-        }))
+        Address::from_sets(vec![iter.into_iter().map(|a| (0, a)).collect()])
     }
 }
 
@@ -162,16 +219,43 @@ impl Builder {
     ///
     /// Returns none if there is no single address in the builder
     pub fn into_address(self) -> Address {
-        Address(Arc::new(Internal {
-            addresses: self.addresses.into_iter()
-                .filter(|vec| vec.len() > 0)
-                .collect(),
-        }))
+        Address::from_sets(self.addresses)
     }
 }
 
 
 impl Address {
+    fn from_sets(sets: Vec<Vec<(Weight, SocketAddr)>>) -> Address {
+        let mut seen = HashSet::new();
+        let mut addresses = Vec::with_capacity(sets.len());
+        for set in sets {
+            let mut result = Vec::<(Weight, SocketAddr)>::new();
+            let mut index = HashMap::<SocketAddr, usize>::new();
+            for (weight, addr) in set {
+                if seen.contains(&addr) {
+                    continue;
+                }
+                match index.get(&addr) {
+                    Some(&idx) => {
+                        let cur = &mut result[idx].0;
+                        *cur = cur.saturating_add(weight);
+                    }
+                    None => {
+                        index.insert(addr, result.len());
+                        result.push((weight, addr));
+                    }
+                }
+            }
+            if result.is_empty() {
+                continue;
+            }
+            seen.extend(result.iter().map(|&(_, addr)| addr));
+            result.sort_by_key(|&(_, addr)| addr);
+            addresses.push(result);
+        }
+        Address(Arc::new(Internal { addresses }))
+    }
+
     /// Select one random address to connect to
     ///
     /// Picks a single address from the set of high priority addresses, with
@@ -179,6 +263,7 @@ impl Address {
     ///
     /// This method is stateless so it can't find out that high priority
     /// addresses are all inaccessible and fallback addresses should be used.
+    /// Use `picker::Picker` for that.
     ///
     /// Returns `None` if address is empty
     pub fn pick_one(&self) -> Option<SocketAddr> {
@@ -216,31 +301,160 @@ impl Address {
     /// Parse a list of strings and put it into an address
     ///
     /// This only uses one layer of addresses with same weights. And is mostly
-    /// useful for unit tests
+    /// useful for unit tests. Use `str::parse` to parse an address having
+    /// multiple priorities and weights.
     pub fn parse_list<I>(iter: I)
         -> Result<Address, AddrParseError>
         where I: IntoIterator,
               I::Item: AsRef<str>
     {
-        Ok(Address(Arc::new(Internal {
-            addresses: vec![
-                iter.into_iter()
-                    .map(|x| x.as_ref().parse().map(|sa| (0, sa)))
-                    .collect::<Result<Vec<_>, _>>()?
-            ],
-        })))
+        Ok(Address::from_sets(vec![
+            iter.into_iter()
+                .map(|x| x.as_ref().parse().map(|sa| (0, sa)))
+                .collect::<Result<Vec<_>, _>>()?
+        ]))
+    }
+
+    /// Returns addresses contained both in this and in the other address
+    ///
+    /// Priorities and weights are taken from `self`, priority of the
+    /// address in `other` doesn't matter.
+    pub fn intersection(&self, other: &Address) -> Address {
+        let other = other.all_addresses();
+        self.filter(|addr| other.contains(addr))
+    }
+
+    /// Returns addresses of this address which are not in the other one
+    ///
+    /// Priorities and weights are taken from `self`. Address is removed
+    /// if it's contained in `other` at any priority.
+    pub fn difference(&self, other: &Address) -> Address {
+        let other = other.all_addresses();
+        self.filter(|addr| !other.contains(addr))
+    }
+
+    fn all_addresses(&self) -> HashSet<SocketAddr> {
+        self.0.addresses.iter()
+            .flat_map(|vec| vec.iter().map(|&(_, addr)| addr))
+            .collect()
+    }
+
+    fn filter<F: Fn(&SocketAddr) -> bool>(&self, f: F) -> Address {
+        Address::from_sets(self.0.addresses.iter()
+            .map(|vec| vec.iter().filter(|&&(_, a)| f(&a)).cloned().collect())
+            .collect())
     }
 }
 
 impl PartialEq for Address {
     fn eq(&self, other: &Address) -> bool {
-        self.0.addresses.len() == other.0.addresses.len() &&
-        self.iter().zip(other.iter()).all(|(s, o)| s == o)
+        Arc::ptr_eq(&self.0, &other.0) ||
+        self.0.addresses == other.0.addresses
     }
 }
 
 impl Eq for Address {}
 
+/// Formats address in the format accepted by `FromStr`
+///
+/// Weight is omitted when it's zero.
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.addresses.is_empty() {
+            return f.write_str("[]");
+        }
+        for (i, set) in self.0.addresses.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" / ")?;
+            }
+            f.write_str("[")?;
+            for (j, &(weight, addr)) in set.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(", ")?;
+                }
+                if weight == 0 {
+                    write!(f, "{}", addr)?;
+                } else {
+                    write!(f, "{} w={}", addr, weight)?;
+                }
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_entry(entry: &str) -> Result<(Weight, SocketAddr), ParseError> {
+    let mut parts = entry.split_whitespace();
+    let addr = match parts.next() {
+        Some(addr) => addr.parse()?,
+        None => return Err(ParseError::InvalidEntry(entry.into())),
+    };
+    let weight = match parts.next() {
+        Some(w) if w.starts_with("w=") => w[2..].parse()?,
+        Some(_) => return Err(ParseError::InvalidEntry(entry.into())),
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return Err(ParseError::InvalidEntry(entry.into()));
+    }
+    Ok((weight, addr))
+}
+
+/// Parses address in the format of
+/// `[1.2.3.4:80 w=10, 1.2.3.5:80 w=5] / [10.0.0.1:80]`
+///
+/// Sets of addresses are listed from the highest priority to the lowest
+/// one. Weight is zero if omitted. Empty address is written as `[]`.
+impl FromStr for Address {
+    type Err = ParseError;
+    fn from_str(value: &str) -> Result<Address, ParseError> {
+        let mut sets = Vec::new();
+        for set in value.split('/') {
+            let set = set.trim();
+            if !set.starts_with('[') || !set.ends_with(']') || set.len() < 2
+            {
+                return Err(ParseError::Brackets(set.into()));
+            }
+            let inner = set[1..set.len()-1].trim();
+            if inner.is_empty() {
+                continue;
+            }
+            sets.push(inner.split(',')
+                .map(|entry| parse_entry(entry.trim()))
+                .collect::<Result<Vec<_>, _>>()?);
+        }
+        Ok(Address::from_sets(sets))
+    }
+}
+
+impl Hash for Address {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.addresses.hash(state)
+    }
+}
+
+
+/// Serializes as a list of sets from the highest priority to the lowest one,
+/// where each set is a list of `[weight, "ip:port"]` pairs
+#[cfg(feature="serde")]
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        self.0.addresses.serialize(serializer)
+    }
+}
+
+/// Deserialized address is normalized the same way as built one
+#[cfg(feature="serde")]
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D)
+        -> Result<Address, D::Error>
+    {
+        Vec::deserialize(deserializer).map(Address::from_sets)
+    }
+}
 
 impl<'a> WeightedSet<'a> {
     /// Select one random address to connect to
@@ -253,7 +467,10 @@ impl<'a> WeightedSet<'a> {
         if self.addresses.len() == 0 {
             return None
         }
-        let total_weight = self.addresses.iter().map(|&(w, _)| w).sum();
+        // weights may be as large as `u64::MAX` after `merge`, so the sum
+        // saturates rather than overflows (it only skews the distribution)
+        let total_weight = self.addresses.iter()
+            .fold(0 as Weight, |sum, &(w, _)| sum.saturating_add(w));
         if total_weight == 0 {
             // All addresses are equal
             return Some(thread_rng().choose(self.addresses).unwrap().1)
@@ -279,26 +496,44 @@ impl<'a> WeightedSet<'a> {
     /// Compares two weighted sets to find out which addresses have been
     /// removed from set or added
     ///
-    /// This doesn't compare weights of the addresses
+    /// This doesn't compare weights of the addresses. Both lists are
+    /// sorted.
     pub fn compare_addresses(&self, other: &WeightedSet)
         -> (Vec<SocketAddr>, Vec<SocketAddr>)
     {
-        // TODO(tailhook) a very naive implementation, optimize
         let mut old = Vec::new();
         let mut new = Vec::new();
-        for &(_, a) in self.addresses {
-            if !other.addresses.iter().find(|&&(_, a1)| a == a1).is_some() {
-                old.push(a);
-            }
-        }
-        for &(_, a) in other.addresses {
-            if !self.addresses.iter().find(|&&(_, a1)| a == a1).is_some() {
-                new.push(a);
+        let mut left = self.addresses.iter().map(|&(_, a)| a).peekable();
+        let mut right = other.addresses.iter().map(|&(_, a)| a).peekable();
+        loop {
+            match (left.peek().cloned(), right.peek().cloned()) {
+                (Some(a), Some(b)) if a == b => {
+                    left.next();
+                    right.next();
+                }
+                (Some(a), Some(b)) if a < b => {
+                    old.push(a);
+                    left.next();
+                }
+                (_, Some(b)) => {
+                    new.push(b);
+                    right.next();
+                }
+                (Some(a), None) => {
+                    old.push(a);
+                    left.next();
+                }
+                (None, None) => break,
             }
         }
         return (old, new);
     }
 
+    /// Returns addresses together with their weights
+    pub(crate) fn entries(&self) -> &'a [(Weight, SocketAddr)] {
+        self.addresses
+    }
+
     /// Number of addresses contained in set
     pub fn len(&self) -> usize {
         self.addresses.len()
@@ -307,26 +542,15 @@ impl<'a> WeightedSet<'a> {
 
 impl<'a> PartialEq for WeightedSet<'a> {
     fn eq(&self, other: &WeightedSet) -> bool {
-        // Very naive implementation, we might optimize it
-        // But we must make sure that order doesn't matter
-        // TODO(tailhook) optimize it, validate in case some adresses
-        // are duplicated
-        if self.addresses.len() != other.addresses.len() {
-            return false;
-        }
-        for &pair in self.addresses {
-            if !other.addresses.iter().find(|&&pair1| pair == pair1).is_some()
-            {
-                return false;
-            }
-        }
-        for &pair in other.addresses {
-            if !self.addresses.iter().find(|&&pair1| pair == pair1).is_some()
-            {
-                return false;
-            }
-        }
-        return true;
+        self.addresses == other.addresses
+    }
+}
+
+impl<'a> Eq for WeightedSet<'a> {}
+
+impl<'a> Hash for WeightedSet<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addresses.hash(state)
     }
 }
 
@@ -335,6 +559,8 @@ impl<'a> PartialEq for WeightedSet<'a> {
 /// Currently we return an Address having only priority 0 with all addresses
 /// contained in every input address's priority zero. Duplicates are removed.
 /// All addresses will have same weight
+///
+/// Use `merge` to keep priorities and weights.
 pub fn union<I>(iter: I) -> Address
     where I: IntoIterator,
           I::Item: AsRef<Address>,
@@ -346,11 +572,80 @@ pub fn union<I>(iter: I) -> Address
     return set.into_iter().collect();
 }
 
+/// Merge `Address` values keeping priorities and weights
+///
+/// Sets of the same priority are merged level by level: `at(0)` of the
+/// result contains addresses of `at(0)` of every input, and so on. If
+/// address is contained in multiple inputs, it's only kept at the highest
+/// priority it has, and its weights at that priority are combined as
+/// specified by `weights`.
+///
+/// Note: zero weights mean "all addresses are equal" only if all weights in
+/// the set are zero, so use `WeightMode::Normalize` when merging sources
+/// having zero weights with ones having non-zero weights.
+pub fn merge<I>(iter: I, weights: WeightMode) -> Address
+    where I: IntoIterator,
+          I::Item: AsRef<Address>,
+{
+    let sources = iter.into_iter().collect::<Vec<_>>();
+    let mut priorities = HashMap::new();
+    for source in &sources {
+        for (pri, set) in source.as_ref().0.addresses.iter().enumerate() {
+            for &(_, addr) in set {
+                let cur = priorities.entry(addr).or_insert(pri);
+                if *cur > pri {
+                    *cur = pri;
+                }
+            }
+        }
+    }
+    let mut levels = Vec::<Vec<(Weight, SocketAddr)>>::new();
+    let mut index = HashMap::<SocketAddr, usize>::new();
+    for source in &sources {
+        for (pri, set) in source.as_ref().0.addresses.iter().enumerate() {
+            let total = set.iter().map(|&(w, _)| w as u128).sum::<u128>();
+            for &(weight, addr) in set {
+                if priorities[&addr] != pri {
+                    continue;
+                }
+                let weight = match weights {
+                    WeightMode::Normalize if total == 0 => {
+                        NORMALIZED_TOTAL / set.len() as Weight
+                    }
+                    WeightMode::Normalize => {
+                        (weight as u128 * NORMALIZED_TOTAL as u128
+                         / total) as Weight
+                    }
+                    WeightMode::Sum | WeightMode::Max => weight,
+                };
+                if levels.len() <= pri {
+                    levels.resize(pri+1, Vec::new());
+                }
+                if let Some(&idx) = index.get(&addr) {
+                    let cur = &mut levels[pri][idx].0;
+                    *cur = match weights {
+                        WeightMode::Max => max(*cur, weight),
+                        WeightMode::Sum | WeightMode::Normalize => {
+                            cur.saturating_add(weight)
+                        }
+                    };
+                } else {
+                    index.insert(addr, levels[pri].len());
+                    levels[pri].push((weight, addr));
+                }
+            }
+        }
+    }
+    Address::from_sets(levels)
+}
+
 #[cfg(test)]
 mod test {
 
-    use super::{Address, union};
+    use super::{Address, Builder, WeightMode, union, merge};
     use std::collections::HashSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
     use std::net::{SocketAddr, IpAddr};
     use std::str::FromStr;
 
@@ -366,8 +661,9 @@ mod test {
         let r = ab.iter()
             .map(|x| x.addresses().collect::<Vec<_>>())
             .collect::<Vec<_>>();
+        // addresses are sorted
         assert_eq!(r, vec![
-            [ "127.0.0.1:1234", "10.0.0.1:3456" ]
+            [ "10.0.0.1:3456", "127.0.0.1:1234" ]
             .iter()
             .map(|x| SocketAddr::from_str(x).unwrap())
             .collect::<Vec<_>>()
@@ -474,6 +770,143 @@ mod test {
         assert_eq!(a.at(0).addresses().collect::<Vec<_>>().len(), 3);
     }
 
+    fn weighted(levels: &[&[(u64, &str)]]) -> Address {
+        let mut builder = Builder::new();
+        for level in levels {
+            builder.add_addresses(&level.iter()
+                .map(|&(w, a)| (w, SocketAddr::from_str(a).unwrap()))
+                .collect::<Vec<_>>());
+        }
+        builder.into_address()
+    }
+
+    #[test]
+    fn test_merge() {
+        let a1 = weighted(&[
+            &[(10, "127.0.0.1:80"), (30, "127.0.0.2:80")],
+            &[(1, "127.0.0.3:80"), (1, "127.0.0.4:80")],
+        ]);
+        let a2 = weighted(&[
+            &[(5, "127.0.0.2:80"), (5, "127.0.0.3:80")],
+            &[(2, "127.0.0.4:80"), (2, "127.0.0.5:80")],
+        ]);
+        assert_eq!(merge(&[&a1, &a2], WeightMode::Sum), weighted(&[
+            &[(10, "127.0.0.1:80"), (35, "127.0.0.2:80"),
+              (5, "127.0.0.3:80")],
+            &[(3, "127.0.0.4:80"), (2, "127.0.0.5:80")],
+        ]));
+        assert_eq!(merge(&[&a1, &a2], WeightMode::Max), weighted(&[
+            &[(10, "127.0.0.1:80"), (30, "127.0.0.2:80"),
+              (5, "127.0.0.3:80")],
+            &[(2, "127.0.0.4:80"), (2, "127.0.0.5:80")],
+        ]));
+        assert_eq!(merge(&[&a1, &a2], WeightMode::Normalize), weighted(&[
+            &[(250000, "127.0.0.1:80"), (1250000, "127.0.0.2:80"),
+              (500000, "127.0.0.3:80")],
+            &[(1000000, "127.0.0.4:80"), (500000, "127.0.0.5:80")],
+        ]));
+    }
+
+    #[test]
+    fn test_pick_huge_weights() {
+        let addr = merge(&[
+            "[127.0.0.1:80 w=18446744073709551615]".parse::<Address>()
+                .unwrap(),
+            "[127.0.0.1:80 w=1, 127.0.0.2:80 w=18446744073709551615]"
+                .parse::<Address>().unwrap(),
+        ], WeightMode::Sum);
+        for _ in 0..100 {
+            assert!(addr.pick_one().is_some());
+        }
+        let addr = merge(&[addr], WeightMode::Normalize);
+        assert_eq!(addr.at(0).len(), 2);
+    }
+
+    #[test]
+    fn test_merge_zero_weights() {
+        let a1 = Address::parse_list(&["127.0.0.1:80", "127.0.0.2:80"])
+            .unwrap();
+        let a2 = weighted(&[&[(3, "127.0.0.3:80"), (1, "127.0.0.1:80")]]);
+        assert_eq!(merge(&[&a1, &a2], WeightMode::Normalize), weighted(&[
+            &[(750000, "127.0.0.1:80"), (500000, "127.0.0.2:80"),
+              (750000, "127.0.0.3:80")],
+        ]));
+        assert_eq!(merge(&[&a1, &a1], WeightMode::Sum), a1);
+    }
+
+    #[test]
+    fn test_intersection_difference() {
+        let a1 = weighted(&[
+            &[(10, "127.0.0.1:80"), (30, "127.0.0.2:80")],
+            &[(1, "127.0.0.3:80")],
+        ]);
+        let a2 = weighted(&[
+            &[(1, "127.0.0.3:80")],
+            &[(1, "127.0.0.2:80"), (1, "127.0.0.4:80")],
+        ]);
+        assert_eq!(a1.intersection(&a2), weighted(&[
+            &[(30, "127.0.0.2:80")],
+            &[(1, "127.0.0.3:80")],
+        ]));
+        assert_eq!(a1.difference(&a2), weighted(&[
+            &[(10, "127.0.0.1:80")],
+        ]));
+        assert_eq!(a2.difference(&a1), weighted(&[
+            &[(1, "127.0.0.4:80")],
+        ]));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let a1 = weighted(&[
+            &[(1, "127.0.0.2:80"), (2, "127.0.0.1:80"), (3, "127.0.0.2:80")],
+            &[],
+            &[(5, "127.0.0.1:80"), (6, "127.0.0.3:80")],
+        ]);
+        let a2 = weighted(&[
+            &[(2, "127.0.0.1:80"), (4, "127.0.0.2:80")],
+            &[(6, "127.0.0.3:80")],
+        ]);
+        assert_eq!(a1, a2);
+        assert_eq!(a1.iter().map(|s| s.len()).collect::<Vec<_>>(), [2, 1]);
+        // sets are sorted
+        assert_eq!(a1.at(0).addresses().collect::<Vec<_>>(), vec![
+            SocketAddr::from_str("127.0.0.1:80").unwrap(),
+            SocketAddr::from_str("127.0.0.2:80").unwrap(),
+        ]);
+        let hash = |a: &Address| {
+            let mut hasher = DefaultHasher::new();
+            a.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&a1), hash(&a2));
+        assert_ne!(a1, weighted(&[
+            &[(2, "127.0.0.1:80"), (3, "127.0.0.2:80")],
+            &[(6, "127.0.0.3:80")],
+        ]));
+        assert_eq!(Address::from(&[][..]), Builder::new().into_address());
+    }
+
+    #[test]
+    fn test_text_format() {
+        let text = "[1.2.3.4:80 w=10, 1.2.3.5:80 w=5] / [10.0.0.1:80]";
+        let a = text.parse::<Address>().unwrap();
+        assert_eq!(a, weighted(&[
+            &[(10, "1.2.3.4:80"), (5, "1.2.3.5:80")],
+            &[(0, "10.0.0.1:80")],
+        ]));
+        assert_eq!(a.to_string(), text);
+        let a = " [ [::1]:80 w=1 ,127.0.0.1:80 ]/[] ".parse::<Address>()
+            .unwrap();
+        assert_eq!(a.to_string(), "[127.0.0.1:80, [::1]:80 w=1]");
+        assert_eq!("[]".parse::<Address>().unwrap().to_string(), "[]");
+        assert!("1.2.3.4:80".parse::<Address>().is_err());
+        assert!("[1.2.3.4:80,]".parse::<Address>().is_err());
+        assert!("[1.2.3.4:80 weight=1]".parse::<Address>().is_err());
+        assert!("[1.2.3.4:80 w=x]".parse::<Address>().is_err());
+        assert!("[1.2.3.4:80 w=1 w=2]".parse::<Address>().is_err());
+    }
+
     fn check_type<S: Stream>(stream: S) -> S
         where S::Item: IntoIterator<Item=SocketAddr>
     {
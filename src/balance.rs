@@ -0,0 +1,456 @@
+//! Load balancing strategies
+//!
+//! A `Strategy` selects a single address from the set of addresses of the
+//! same priority. Use it with `picker::Picker` (which also handles
+//! priorities and failures), or call `Strategy::pick_from` directly to
+//! select one of the highest priority addresses.
+//!
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+
+use rand::{thread_rng, Rng};
+use addr::{Address, Weight};
+
+
+/// A strategy of selecting an address from a weighted set
+pub trait Strategy {
+    /// Pick one of the candidates
+    ///
+    /// Candidates are addresses of the same priority with their weights.
+    /// `key` is a hash of the request key (see `hash_key`), it's only
+    /// used by strategies that provide affinity, like `Rendezvous`,
+    /// others ignore it.
+    ///
+    /// Returns `None` only if there are no candidates.
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], key: u64)
+        -> Option<SocketAddr>;
+
+    /// Pick one of the highest priority addresses
+    fn pick_from(&mut self, address: &Address, key: u64)
+        -> Option<SocketAddr>
+    {
+        self.pick(address.at(0).entries(), key)
+    }
+}
+
+/// Returns a hash of the request key suitable for `Strategy::pick`
+///
+/// The hash is 64-bit FNV-1a with the standard offset basis followed by
+/// the MurmurHash3 finalizer (to spread bits of similar keys), no random
+/// keys are used. Integers are hashed as little-endian bytes and `usize`
+/// is always hashed as 64-bit. So the hash is stable between runs,
+/// processes and platforms (e.g. multiple instances behind a balancer map
+/// the key to the same address). Note: the value still depends on the
+/// `Hash` implementation of the key, which for std types may change
+/// between Rust versions.
+pub fn hash_key<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A 64-bit FNV-1a hasher with `fmix64` finalizer of MurmurHash3
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+    fn finish(&self) -> u64 {
+        let mut h = self.0;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        h ^ (h >> 33)
+    }
+}
+
+/// Weighted random choice (the same as `WeightedSet::pick_one`)
+#[derive(Debug, Clone, Default)]
+pub struct WeightedRandom;
+
+/// Smooth weighted round-robin (the one used in nginx)
+///
+/// Addresses are selected in turn proportionally to their weights, but
+/// spread evenly: for weights `5, 1, 1` the sequence is
+/// `a a b a c a a` rather than `a a a a a b c`.
+///
+/// The state is kept for the last set of candidates, when the set changes
+/// addresses that are still in the set keep their position in the sequence.
+#[derive(Debug, Clone, Default)]
+pub struct RoundRobin {
+    candidates: Vec<(Weight, SocketAddr)>,
+    current: Vec<i128>,
+}
+
+/// Power of two choices
+///
+/// Two distinct addresses are selected randomly according to their weights
+/// and the one having smaller load is picked. Load is returned by the
+/// user-supplied function.
+#[derive(Debug, Clone)]
+pub struct PowerOfTwo<F> {
+    load: F,
+}
+
+/// Picks address having least number of outstanding requests
+///
+/// User must call `started` and `finished` for each request made to the
+/// address. Number of requests is divided by the weight of the address
+/// before comparison. Ties are resolved randomly.
+#[derive(Debug, Clone, Default)]
+pub struct LeastOutstanding {
+    outstanding: HashMap<SocketAddr, u64>,
+}
+
+/// Rendezvous (highest random weight) hashing
+///
+/// Requests having the same key are sent to the same address as long as
+/// it's in the set, and when an address is removed only keys that were
+/// mapped to it are moved to other addresses. This is useful for cache
+/// affinity. Weights are respected, i.e. an address having twice the
+/// weight gets twice as many keys.
+#[derive(Debug, Clone, Default)]
+pub struct Rendezvous;
+
+fn weighted_random(candidates: &[(Weight, SocketAddr)]) -> Option<usize> {
+    if candidates.is_empty() {
+        return None;
+    }
+    let total_weight = candidates.iter()
+        .fold(0 as Weight, |sum, &(w, _)| sum.saturating_add(w));
+    if total_weight == 0 {
+        return Some(thread_rng().gen_range(0, candidates.len()));
+    }
+    let mut n = thread_rng().gen_range(0, total_weight);
+    for (idx, &(w, _)) in candidates.iter().enumerate() {
+        if n < w {
+            return Some(idx);
+        }
+        n -= w;
+    }
+    unreachable!();
+}
+
+/// Weight to use when all weights are zero (this means equal weights)
+fn effective_weight(candidates: &[(Weight, SocketAddr)], weight: Weight)
+    -> Weight
+{
+    if candidates.iter().all(|&(w, _)| w == 0) { 1 } else { weight }
+}
+
+impl Strategy for WeightedRandom {
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], _key: u64)
+        -> Option<SocketAddr>
+    {
+        weighted_random(candidates).map(|idx| candidates[idx].1)
+    }
+}
+
+impl RoundRobin {
+    /// Create a new round-robin strategy
+    pub fn new() -> RoundRobin {
+        RoundRobin::default()
+    }
+}
+
+impl Strategy for RoundRobin {
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], _key: u64)
+        -> Option<SocketAddr>
+    {
+        if self.candidates[..] != candidates[..] {
+            let old = self.candidates.iter().map(|&(_, a)| a)
+                .zip(self.current.iter().cloned())
+                .collect::<HashMap<_, _>>();
+            self.current = candidates.iter()
+                .map(|&(_, a)| old.get(&a).cloned().unwrap_or(0))
+                .collect();
+            self.candidates = candidates.to_vec();
+        }
+        let equal = candidates.iter().all(|&(w, _)| w == 0);
+        // weights are up to `u64::MAX`, so sums are computed in `i128`
+        let mut total = 0i128;
+        let mut best: Option<usize> = None;
+        for (idx, &(weight, _)) in candidates.iter().enumerate() {
+            let weight = if equal { 1 } else { i128::from(weight) };
+            self.current[idx] = self.current[idx].saturating_add(weight);
+            total = total.saturating_add(weight);
+            if best.map(|b| self.current[idx] > self.current[b])
+                .unwrap_or(true)
+            {
+                best = Some(idx);
+            }
+        }
+        best.map(|idx| {
+            self.current[idx] = self.current[idx].saturating_sub(total);
+            candidates[idx].1
+        })
+    }
+}
+
+impl<F: FnMut(SocketAddr) -> u64> PowerOfTwo<F> {
+    /// Create a strategy using `load` function to compare addresses
+    pub fn new(load: F) -> PowerOfTwo<F> {
+        PowerOfTwo { load }
+    }
+}
+
+impl<F: FnMut(SocketAddr) -> u64> Strategy for PowerOfTwo<F> {
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], _key: u64)
+        -> Option<SocketAddr>
+    {
+        let idx = weighted_random(candidates)?;
+        let a = candidates[idx].1;
+        let rest = candidates.iter().enumerate()
+            .filter(|&(i, _)| i != idx)
+            .map(|(_, &c)| c)
+            .collect::<Vec<_>>();
+        let b = match weighted_random(&rest) {
+            Some(idx) => rest[idx].1,
+            None => return Some(a),
+        };
+        if (self.load)(a) <= (self.load)(b) {
+            Some(a)
+        } else {
+            Some(b)
+        }
+    }
+}
+
+impl LeastOutstanding {
+    /// Create a new strategy with no requests in progress
+    pub fn new() -> LeastOutstanding {
+        LeastOutstanding::default()
+    }
+    /// Report that request to the address is started
+    pub fn started(&mut self, addr: SocketAddr) {
+        *self.outstanding.entry(addr).or_insert(0) += 1;
+    }
+    /// Report that request to the address is finished (or failed)
+    pub fn finished(&mut self, addr: SocketAddr) {
+        let remove = match self.outstanding.get_mut(&addr) {
+            Some(n) => {
+                *n = n.saturating_sub(1);
+                *n == 0
+            }
+            None => false,
+        };
+        if remove {
+            self.outstanding.remove(&addr);
+        }
+    }
+    /// Returns number of requests in progress to the address
+    pub fn outstanding(&self, addr: SocketAddr) -> u64 {
+        self.outstanding.get(&addr).cloned().unwrap_or(0)
+    }
+}
+
+impl Strategy for LeastOutstanding {
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], _key: u64)
+        -> Option<SocketAddr>
+    {
+        let mut best = Vec::new();
+        let mut best_load = 0f64;
+        for &(weight, addr) in candidates {
+            let weight = effective_weight(candidates, weight);
+            if weight == 0 {
+                continue;
+            }
+            let load = self.outstanding(addr) as f64 / weight as f64;
+            if best.is_empty() || load < best_load {
+                best.clear();
+                best_load = load;
+            }
+            if load <= best_load {
+                best.push(addr);
+            }
+        }
+        if best.is_empty() {
+            // only zero-weight addresses have no requests
+            return weighted_random(candidates).map(|idx| candidates[idx].1);
+        }
+        thread_rng().choose(&best).cloned()
+    }
+}
+
+impl Rendezvous {
+    /// Create a rendezvous hashing strategy
+    pub fn new() -> Rendezvous {
+        Rendezvous
+    }
+}
+
+impl Strategy for Rendezvous {
+    fn pick(&mut self, candidates: &[(Weight, SocketAddr)], key: u64)
+        -> Option<SocketAddr>
+    {
+        let mut best = None;
+        let mut best_score = 0f64;
+        for &(weight, addr) in candidates {
+            let weight = effective_weight(candidates, weight);
+            let hash = hash_key(&(key, addr));
+            // uniformly distributed number in (0, 1)
+            let x = ((hash >> 11) as f64 + 1.0) / ((1u64 << 53) as f64 + 2.0);
+            let score = weight as f64 / -x.ln();
+            if best.is_none() || score > best_score {
+                best = Some(addr);
+                best_score = score;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use super::{Strategy, RoundRobin, PowerOfTwo, LeastOutstanding};
+    use super::{Rendezvous, WeightedRandom, hash_key};
+
+    fn sa(x: &str) -> SocketAddr {
+        x.parse().unwrap()
+    }
+
+    fn set(x: &[(u64, &str)]) -> Vec<(u64, SocketAddr)> {
+        x.iter().map(|&(w, a)| (w, sa(a))).collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(WeightedRandom.pick(&[], 0), None);
+        assert_eq!(RoundRobin::new().pick(&[], 0), None);
+        assert_eq!(PowerOfTwo::new(|_| 0).pick(&[], 0), None);
+        assert_eq!(LeastOutstanding::new().pick(&[], 0), None);
+        assert_eq!(Rendezvous::new().pick(&[], 0), None);
+    }
+
+    #[test]
+    fn smooth_round_robin() {
+        let s = set(&[(5, "1.0.0.1:1"), (1, "1.0.0.2:1"), (1, "1.0.0.3:1")]);
+        let mut rr = RoundRobin::new();
+        let seq = (0..7).map(|_| rr.pick(&s, 0).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(seq, vec!["1.0.0.1:1", "1.0.0.1:1", "1.0.0.2:1",
+                             "1.0.0.1:1", "1.0.0.3:1", "1.0.0.1:1",
+                             "1.0.0.1:1"]);
+        let s = set(&[(0, "1.0.0.1:1"), (0, "1.0.0.2:1")]);
+        let seq = (0..4).map(|_| rr.pick(&s, 0).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(seq, vec!["1.0.0.1:1", "1.0.0.2:1",
+                             "1.0.0.1:1", "1.0.0.2:1"]);
+    }
+
+    #[test]
+    fn round_robin_keeps_state() {
+        let s = set(&[(1, "1.0.0.1:1"), (1, "1.0.0.2:1"), (1, "1.0.0.3:1")]);
+        let mut rr = RoundRobin::new();
+        assert_eq!(rr.pick(&s, 0), Some(sa("1.0.0.1:1")));
+        let s = set(&[(1, "1.0.0.3:1"), (1, "1.0.0.2:1")]);
+        let seq = (0..3).map(|_| rr.pick(&s, 0).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(seq, vec!["1.0.0.3:1", "1.0.0.2:1", "1.0.0.3:1"]);
+    }
+
+    #[test]
+    fn round_robin_huge_weights() {
+        let max = u64::MAX;
+        let s = set(&[(max, "1.0.0.1:1"), (max, "1.0.0.2:1"),
+                      (max / 2, "1.0.0.3:1")]);
+        let mut rr = RoundRobin::new();
+        let seq = (0..5).map(|_| rr.pick(&s, 0).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(seq, vec!["1.0.0.1:1", "1.0.0.2:1", "1.0.0.3:1",
+                             "1.0.0.1:1", "1.0.0.2:1"]);
+    }
+
+    #[test]
+    fn random_huge_weights() {
+        let s = set(&[(u64::MAX, "1.0.0.1:1"), (u64::MAX, "1.0.0.2:1")]);
+        assert!(WeightedRandom.pick(&s, 0).is_some());
+        assert!(PowerOfTwo::new(|_| 0).pick(&s, 0).is_some());
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash_key(&0u64), 0x7bd3_144f_29c0_cc9e);
+        // length of the slice is hashed as 64-bit on every platform
+        assert_eq!(hash_key(&[1u8, 2][..]), 0x42f4_6874_ce6d_c7ad);
+        assert_eq!(hash_key("abc"), 0x3ee0_641e_1a67_4131);
+        assert_eq!(hash_key(&(7u64, sa("1.0.0.1:1"))),
+                   hash_key(&(7u64, sa("1.0.0.1:1"))));
+    }
+
+    #[test]
+    fn power_of_two() {
+        let s = set(&[(1, "1.0.0.1:1"), (1, "1.0.0.2:1")]);
+        let busy = sa("1.0.0.1:1");
+        let mut p2c = PowerOfTwo::new(|a| if a == busy { 100 } else { 0 });
+        for _ in 0..100 {
+            assert_eq!(p2c.pick(&s, 0), Some(sa("1.0.0.2:1")));
+        }
+    }
+
+    #[test]
+    fn least_outstanding() {
+        let s = set(&[(2, "1.0.0.1:1"), (1, "1.0.0.2:1")]);
+        let mut lo = LeastOutstanding::new();
+        lo.started(sa("1.0.0.1:1"));
+        assert_eq!(lo.pick(&s, 0), Some(sa("1.0.0.2:1")));
+        lo.started(sa("1.0.0.2:1"));
+        // 1/2 < 1/1
+        assert_eq!(lo.pick(&s, 0), Some(sa("1.0.0.1:1")));
+        lo.finished(sa("1.0.0.2:1"));
+        lo.finished(sa("1.0.0.2:1"));
+        assert_eq!(lo.outstanding(sa("1.0.0.2:1")), 0);
+        assert_eq!(lo.pick(&s, 0), Some(sa("1.0.0.2:1")));
+    }
+
+    #[test]
+    fn rendezvous() {
+        let s = set(&[(1, "1.0.0.1:1"), (1, "1.0.0.2:1"), (2, "1.0.0.3:1")]);
+        let mut r = Rendezvous::new();
+        let mut counts = HashMap::new();
+        let mut mapping = Vec::new();
+        for key in 0..4000 {
+            let addr = r.pick(&s, key).unwrap();
+            assert_eq!(r.pick(&s, key), Some(addr));
+            *counts.entry(addr).or_insert(0) += 1;
+            mapping.push(addr);
+        }
+        let count = |x| counts[&sa(x)];
+        assert!(count("1.0.0.1:1") > 800 && count("1.0.0.1:1") < 1200);
+        assert!(count("1.0.0.2:1") > 800 && count("1.0.0.2:1") < 1200);
+        assert!(count("1.0.0.3:1") > 1800 && count("1.0.0.3:1") < 2200);
+        // removing an address only moves keys that were mapped to it
+        let s2 = set(&[(1, "1.0.0.1:1"), (2, "1.0.0.3:1")]);
+        for (key, &addr) in mapping.iter().enumerate() {
+            if addr != sa("1.0.0.2:1") {
+                assert_eq!(r.pick(&s2, key as u64), Some(addr));
+            }
+        }
+    }
+}
@@ -1,9 +1,40 @@
 //! A number of combinators returned by methods on traits
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use futures::{Async, Future, Stream};
+use futures::task;
 use futures::future::{FutureResult, err};
+use rand::{thread_rng, Rng};
+use tokio_timer::Delay;
 use {Name, Address, IpList, Error};
 use {Resolve, Subscribe, HostResolve, HostSubscribe};
 
+pub(crate) mod boxed;
+mod cache;
+mod coalesce;
+mod fallback;
+mod family;
+mod last_good;
+mod many;
+mod retry;
+mod timeout;
+
+pub use self::boxed::{BoxResolver, BoxHostResolver};
+pub use self::boxed::{BoxSubscriber, BoxHostSubscriber};
+pub use self::boxed::{BoxFuture, BoxHostFuture, BoxStream, BoxHostStream};
+pub use self::cache::{CachingResolver, CacheFuture};
+pub use self::coalesce::{CoalescingResolver, CoalesceFuture};
+pub use self::fallback::{Fallback, FallbackChain};
+pub use self::fallback::{FallbackFuture, FallbackStream};
+pub use self::fallback::{ChainFuture, ChainStream};
+pub use self::family::{FamilyFilter, FamilyPolicy};
+pub use self::family::{FamilyFuture, FamilyStream};
+pub use self::last_good::{LastKnownGood, LastKnownGoodStream};
+pub use self::many::SubscribeMany;
+pub use self::retry::{Retry, RetryFuture, RetryStream};
+pub use self::timeout::{Timeout, TimeoutFuture, TimeoutStream};
+
 /// A stream returned from subscription on FrozenResolver
 ///
 /// This stream basically yields a first value of a future and never returns
@@ -46,6 +77,57 @@ pub struct NullHostResolver<R> {
     pub(crate) resolver: R,
 }
 
+/// A subscriber that periodically polls the underlying resolver
+///
+/// You can create it with `Resolve::interval_subscriber` or
+/// `HostResolve::interval_host_subscriber`
+#[derive(Debug)]
+pub struct IntervalSubscriber<R> {
+    pub(crate) resolver: Arc<R>,
+    pub(crate) period: Duration,
+}
+
+/// A stream returned from subscription on `IntervalSubscriber`
+///
+/// This stream resolves a name every period of time and yields a value
+/// only when it differs from the previous one. Temporary errors are skipped
+/// (the name is resolved again on the next tick), other errors are returned
+/// and so they shut down the stream.
+#[derive(Debug)]
+pub struct IntervalStream<R, F: Future> {
+    resolver: Arc<R>,
+    resolve: fn(&R, &Name) -> F,
+    name: Name,
+    period: Duration,
+    future: Option<F>,
+    timer: Delay,
+    last_value: Option<F::Item>,
+}
+
+/// A resolver that turns hostname resolver into a service resolver by
+/// adding a default port to every address
+///
+/// You can create it with `HostResolve::with_default_port`
+#[derive(Debug)]
+pub struct WithDefaultPort<R> {
+    pub(crate) resolver: R,
+    pub(crate) port: u16,
+}
+
+/// A future returned by `WithDefaultPort::resolve`
+#[derive(Debug)]
+pub struct PortFuture<F> {
+    future: F,
+    port: u16,
+}
+
+/// A stream returned by `WithDefaultPort::subscribe`
+#[derive(Debug)]
+pub struct PortStream<S> {
+    stream: S,
+    port: u16,
+}
+
 impl<F: Future> Stream for StreamOnce<F> {
     type Item = F::Item;
     type Error = F::Error;
@@ -121,3 +203,171 @@ impl<R: HostResolve> HostSubscribe for FrozenSubscriber<R> {
         StreamOnce { future: Some(self.resolve_host(name)) }
     }
 }
+
+impl<R: Resolve> Resolve for IntervalSubscriber<R> {
+    type Future = R::Future;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        self.resolver.resolve(name)
+    }
+}
+
+impl<R: Resolve> Subscribe for IntervalSubscriber<R> {
+    type Stream = IntervalStream<R, R::Future>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        IntervalStream::new(&self.resolver, R::resolve, name, self.period)
+    }
+}
+
+impl<R: HostResolve> HostResolve for IntervalSubscriber<R> {
+    type HostFuture = R::HostFuture;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        self.resolver.resolve_host(name)
+    }
+}
+
+impl<R: HostResolve> HostSubscribe for IntervalSubscriber<R> {
+    type HostStream = IntervalStream<R, R::HostFuture>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        IntervalStream::new(&self.resolver, R::resolve_host,
+                            name, self.period)
+    }
+}
+
+/// Returns a random duration within 10% of the `period`
+///
+/// This is used to spread resolution requests of many subscriptions
+/// created at the same time
+fn jitter(period: Duration) -> Duration {
+    let nanos = period.as_secs() as f64 * 1e9 + period.subsec_nanos() as f64;
+    let nanos = nanos * thread_rng().gen_range(0.9, 1.1);
+    Duration::new((nanos / 1e9) as u64, (nanos % 1e9) as u32)
+}
+
+impl<R, F> IntervalStream<R, F>
+    where F: Future<Error=Error>
+{
+    fn new(resolver: &Arc<R>, resolve: fn(&R, &Name) -> F,
+           name: &Name, period: Duration)
+        -> IntervalStream<R, F>
+    {
+        IntervalStream {
+            future: Some(resolve(resolver, name)),
+            resolver: resolver.clone(),
+            resolve,
+            name: name.clone(),
+            period,
+            timer: Delay::new(Instant::now() + period),
+            last_value: None,
+        }
+    }
+}
+
+impl<R, F> Stream for IntervalStream<R, F>
+    where F: Future<Error=Error>,
+          F::Item: PartialEq + Clone,
+{
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<F::Item>>, Error> {
+        let mut restarted = false;
+        loop {
+            if let Some(mut future) = self.future.take() {
+                match future.poll() {
+                    Ok(Async::Ready(value)) => {
+                        self.timer.reset(Instant::now() + jitter(self.period));
+                        if self.last_value.as_ref() != Some(&value) {
+                            self.last_value = Some(value.clone());
+                            return Ok(Async::Ready(Some(value)));
+                        }
+                    }
+                    Ok(Async::NotReady) => {
+                        self.future = Some(future);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(Error::TemporaryError(_)) | Err(Error::Timeout) => {
+                        self.timer.reset(Instant::now() + jitter(self.period));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            match self.timer.poll() {
+                // with a zero (or tiny) period and a synchronous resolver
+                // this would never return, so let other tasks run
+                Ok(Async::Ready(())) if restarted => {
+                    task::current().notify();
+                    return Ok(Async::NotReady);
+                }
+                Ok(Async::Ready(())) => {
+                    restarted = true;
+                    self.future = Some((self.resolve)(
+                        &self.resolver, &self.name));
+                }
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => return Err(Error::TemporaryError(Box::new(e))),
+            }
+        }
+    }
+}
+
+impl<R: HostResolve> Resolve for WithDefaultPort<R> {
+    type Future = PortFuture<R::HostFuture>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        PortFuture {
+            future: self.resolver.resolve_host(name),
+            port: self.port,
+        }
+    }
+}
+
+impl<R: HostSubscribe> Subscribe for WithDefaultPort<R> {
+    type Stream = PortStream<R::HostStream>;
+    type Error = R::HostError;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        PortStream {
+            stream: self.resolver.subscribe_host(name),
+            port: self.port,
+        }
+    }
+}
+
+impl<R: HostResolve> HostResolve for WithDefaultPort<R> {
+    type HostFuture = R::HostFuture;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        self.resolver.resolve_host(name)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for WithDefaultPort<R> {
+    type HostStream = R::HostStream;
+    type HostError = R::HostError;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.resolver.subscribe_host(name)
+    }
+}
+
+impl<F: Future<Item=IpList>> Future for PortFuture<F> {
+    type Item = Address;
+    type Error = F::Error;
+    fn poll(&mut self) -> Result<Async<Address>, F::Error> {
+        match self.future.poll()? {
+            Async::Ready(ips) => Ok(Async::Ready(ips.with_port(self.port))),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<S: Stream<Item=IpList>> Stream for PortStream<S> {
+    type Item = Address;
+    type Error = S::Error;
+    fn poll(&mut self) -> Result<Async<Option<Address>>, S::Error> {
+        match self.stream.poll()? {
+            Async::Ready(Some(ips)) => {
+                Ok(Async::Ready(Some(ips.with_port(self.port))))
+            }
+            Async::Ready(None) => Ok(Async::Ready(None)),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::sync::Arc;
+
+use futures::{Future, Stream};
+use {Name, Address, IpList, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// A boxed future returned by `BoxResolver`
+pub type BoxFuture = Box<dyn Future<Item=Address, Error=Error> + Send>;
+
+/// A boxed future returned by `BoxHostResolver`
+pub type BoxHostFuture = Box<dyn Future<Item=IpList, Error=Error> + Send>;
+
+/// A boxed stream returned by `BoxSubscriber`
+pub type BoxStream = Box<dyn Stream<Item=Address, Error=Error> + Send>;
+
+/// A boxed stream returned by `BoxHostSubscriber`
+pub type BoxHostStream = Box<dyn Stream<Item=IpList, Error=Error> + Send>;
+
+/// A type-erased `Resolve` implementation
+///
+/// This allows to store resolvers of different types in a single
+/// collection or to choose resolver at runtime.
+///
+/// Create it with `Resolve::boxed_resolver`. Cloning is cheap, as
+/// the resolver is kept behind an `Arc`.
+///
+/// There is no single `boxed()` method: most resolvers implement several
+/// of the resolution traits, so a method of the same name in every trait
+/// could only be called as `Resolve::boxed(resolver)`. Also the wrapped
+/// resolver isn't required to implement `Debug`, so `Debug` of the boxed
+/// one only prints the type name.
+#[derive(Clone)]
+pub struct BoxResolver(Arc<dyn ErasedResolve>);
+
+/// A type-erased `HostResolve` implementation
+///
+/// Create it with `HostResolve::boxed_host_resolver`
+#[derive(Clone)]
+pub struct BoxHostResolver(Arc<dyn ErasedHostResolve>);
+
+/// A type-erased `Subscribe` implementation
+///
+/// Errors of the stream are converted into `abstract_ns::Error`.
+///
+/// Create it with `Subscribe::boxed_subscriber`
+#[derive(Clone)]
+pub struct BoxSubscriber(Arc<dyn ErasedSubscribe>);
+
+/// A type-erased `HostSubscribe` implementation
+///
+/// Errors of the stream are converted into `abstract_ns::Error`.
+///
+/// Create it with `HostSubscribe::boxed_host_subscriber`
+#[derive(Clone)]
+pub struct BoxHostSubscriber(Arc<dyn ErasedHostSubscribe>);
+
+pub(crate) trait ErasedResolve: Send + Sync {
+    fn resolve(&self, name: &Name) -> BoxFuture;
+}
+
+pub(crate) trait ErasedHostResolve: Send + Sync {
+    fn resolve_host(&self, name: &Name) -> BoxHostFuture;
+}
+
+pub(crate) trait ErasedSubscribe: Send + Sync {
+    fn subscribe(&self, name: &Name) -> BoxStream;
+}
+
+pub(crate) trait ErasedHostSubscribe: Send + Sync {
+    fn subscribe_host(&self, name: &Name) -> BoxHostStream;
+}
+
+/// All four erased traits at once
+///
+/// This is used to keep a single boxed object per resolver when it is
+/// used for both service and host names, e.g. in `SuffixRouter`.
+pub(crate) trait ErasedResolver
+    : ErasedResolve + ErasedHostResolve + ErasedSubscribe + ErasedHostSubscribe
+{}
+
+impl<R> ErasedResolver for R
+    where R: ErasedResolve + ErasedHostResolve,
+          R: ErasedSubscribe + ErasedHostSubscribe,
+{}
+
+impl<R> ErasedResolve for R
+    where R: Resolve + Send + Sync,
+          R::Future: Send + 'static,
+{
+    fn resolve(&self, name: &Name) -> BoxFuture {
+        Box::new(Resolve::resolve(self, name))
+    }
+}
+
+impl<R> ErasedHostResolve for R
+    where R: HostResolve + Send + Sync,
+          R::HostFuture: Send + 'static,
+{
+    fn resolve_host(&self, name: &Name) -> BoxHostFuture {
+        Box::new(HostResolve::resolve_host(self, name))
+    }
+}
+
+impl<R> ErasedSubscribe for R
+    where R: Subscribe + Send + Sync,
+          R::Stream: Send + 'static,
+{
+    fn subscribe(&self, name: &Name) -> BoxStream {
+        Box::new(Subscribe::subscribe(self, name).map_err(|e| e.into()))
+    }
+}
+
+impl<R> ErasedHostSubscribe for R
+    where R: HostSubscribe + Send + Sync,
+          R::HostStream: Send + 'static,
+{
+    fn subscribe_host(&self, name: &Name) -> BoxHostStream {
+        Box::new(HostSubscribe::subscribe_host(self, name)
+            .map_err(|e| e.into()))
+    }
+}
+
+impl fmt::Debug for BoxResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BoxResolver")
+    }
+}
+
+impl fmt::Debug for BoxHostResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BoxHostResolver")
+    }
+}
+
+impl fmt::Debug for BoxSubscriber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BoxSubscriber")
+    }
+}
+
+impl fmt::Debug for BoxHostSubscriber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BoxHostSubscriber")
+    }
+}
+
+impl BoxResolver {
+    pub(crate) fn new<R>(resolver: R) -> BoxResolver
+        where R: Resolve + Send + Sync + 'static,
+              R::Future: Send + 'static,
+    {
+        BoxResolver(Arc::new(resolver))
+    }
+}
+
+impl BoxHostResolver {
+    pub(crate) fn new<R>(resolver: R) -> BoxHostResolver
+        where R: HostResolve + Send + Sync + 'static,
+              R::HostFuture: Send + 'static,
+    {
+        BoxHostResolver(Arc::new(resolver))
+    }
+}
+
+impl BoxSubscriber {
+    pub(crate) fn new<R>(resolver: R) -> BoxSubscriber
+        where R: Subscribe + Send + Sync + 'static,
+              R::Stream: Send + 'static,
+    {
+        BoxSubscriber(Arc::new(resolver))
+    }
+}
+
+impl BoxHostSubscriber {
+    pub(crate) fn new<R>(resolver: R) -> BoxHostSubscriber
+        where R: HostSubscribe + Send + Sync + 'static,
+              R::HostStream: Send + 'static,
+    {
+        BoxHostSubscriber(Arc::new(resolver))
+    }
+}
+
+impl Resolve for BoxResolver {
+    type Future = BoxFuture;
+    fn resolve(&self, name: &Name) -> BoxFuture {
+        self.0.resolve(name)
+    }
+}
+
+impl HostResolve for BoxHostResolver {
+    type HostFuture = BoxHostFuture;
+    fn resolve_host(&self, name: &Name) -> BoxHostFuture {
+        self.0.resolve_host(name)
+    }
+}
+
+impl Subscribe for BoxSubscriber {
+    type Stream = BoxStream;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> BoxStream {
+        self.0.subscribe(name)
+    }
+}
+
+impl HostSubscribe for BoxHostSubscriber {
+    type HostStream = BoxHostStream;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> BoxHostStream {
+        self.0.subscribe_host(name)
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt;
+use std::collections::{HashMap, BTreeMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future};
+use {Name, Address, IpList, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+type TtlCallback<T> = Arc<dyn Fn(&Name, &T) -> Option<Duration> + Send + Sync>;
+
+/// A resolver that caches results of the underlying resolver
+///
+/// Successfully resolved names are cached for a `ttl` and names that are
+/// not found (i.e. `Error::NameNotFound`) are cached for a `negative_ttl`.
+/// Other errors are never cached. Resolution traits don't expose TTL of
+/// the records, so the same `ttl` is used for all names, unless a callback
+/// is set with `address_ttl_by` or `host_ttl_by` (the callback receives
+/// the resolved value, so TTL can be derived from it).
+///
+/// Zero TTL means the value is not put into the cache at all, so it doesn't
+/// evict other names.
+///
+/// Number of names in the cache is limited, least recently used names are
+/// evicted first.
+///
+/// Note: addresses and hosts are cached separately, so the capacity limit
+/// is applied to each of them independently.
+///
+/// Subscriptions are passed to the underlying resolver as is.
+///
+/// Cloned resolver shares the cache with the original one.
+#[derive(Clone)]
+pub struct CachingResolver<R> {
+    resolver: R,
+    ttl: Duration,
+    address_ttl_by: Option<TtlCallback<Address>>,
+    host_ttl_by: Option<TtlCallback<IpList>>,
+    negative_ttl: Duration,
+    addresses: Arc<Mutex<Cache<Address>>>,
+    hosts: Arc<Mutex<Cache<IpList>>>,
+}
+
+/// A future returned by `CachingResolver`
+///
+/// It either returns the value from the cache or resolves the name and
+/// puts it into the cache.
+pub struct CacheFuture<F: Future> {
+    cached: Option<Result<F::Item, Error>>,
+    future: Option<F>,
+    name: Name,
+    ttl: Duration,
+    ttl_by: Option<TtlCallback<F::Item>>,
+    negative_ttl: Duration,
+    cache: Arc<Mutex<Cache<F::Item>>>,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    value: Option<T>,
+    expires: Instant,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Cache<T> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<Name, Entry<T>>,
+    order: BTreeMap<u64, Name>,
+}
+
+impl<R> CachingResolver<R> {
+    /// Create a caching resolver that keeps up to `capacity` names
+    ///
+    /// By default successful results are cached for a minute and
+    /// `NameNotFound` errors are cached for 5 seconds.
+    pub fn new(resolver: R, capacity: usize) -> CachingResolver<R> {
+        CachingResolver {
+            resolver,
+            ttl: Duration::new(60, 0),
+            address_ttl_by: None,
+            host_ttl_by: None,
+            negative_ttl: Duration::new(5, 0),
+            addresses: Arc::new(Mutex::new(Cache::new(capacity))),
+            hosts: Arc::new(Mutex::new(Cache::new(capacity))),
+        }
+    }
+    /// Set time for which successfully resolved names are cached
+    pub fn ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = ttl;
+        self
+    }
+    /// Set a callback which returns time to cache the resolved address for
+    ///
+    /// The callback is called with the name and the value when the name is
+    /// successfully resolved, if it returns `None` the default `ttl` is
+    /// used. This allows to respect TTL of the records returned by the
+    /// underlying resolver.
+    pub fn address_ttl_by<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&Name, &Address) -> Option<Duration>,
+              F: Send + Sync + 'static,
+    {
+        self.address_ttl_by = Some(Arc::new(f));
+        self
+    }
+    /// Set a callback which returns time to cache the resolved host for
+    ///
+    /// Works the same as `address_ttl_by` but for `resolve_host`.
+    pub fn host_ttl_by<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&Name, &IpList) -> Option<Duration>,
+              F: Send + Sync + 'static,
+    {
+        self.host_ttl_by = Some(Arc::new(f));
+        self
+    }
+    /// Set time for which `NameNotFound` errors are cached
+    ///
+    /// Zero duration effectively disables negative caching
+    pub fn negative_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.negative_ttl = ttl;
+        self
+    }
+    /// Remove the name from the cache
+    ///
+    /// Both service and host resolution results are removed
+    pub fn invalidate(&self, name: &Name) {
+        self.addresses.lock().expect("cache is not poisoned").remove(name);
+        self.hosts.lock().expect("cache is not poisoned").remove(name);
+    }
+    /// Remove all names from the cache
+    pub fn flush(&self) {
+        self.addresses.lock().expect("cache is not poisoned").clear();
+        self.hosts.lock().expect("cache is not poisoned").clear();
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for CachingResolver<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachingResolver")
+        .field("resolver", &self.resolver)
+        .field("ttl", &self.ttl)
+        .field("negative_ttl", &self.negative_ttl)
+        .field("addresses", &self.addresses)
+        .field("hosts", &self.hosts)
+        .finish()
+    }
+}
+
+impl<F> fmt::Debug for CacheFuture<F>
+    where F: Future + fmt::Debug,
+          F::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheFuture")
+        .field("cached", &self.cached)
+        .field("future", &self.future)
+        .field("name", &self.name)
+        .field("ttl", &self.ttl)
+        .field("negative_ttl", &self.negative_ttl)
+        .finish()
+    }
+}
+
+impl<R: Resolve> Resolve for CachingResolver<R> {
+    type Future = CacheFuture<R::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        if let Some(value) = self.addresses.lock()
+            .expect("cache is not poisoned").get(name)
+        {
+            return CacheFuture::cached(name, value, &self.addresses);
+        }
+        CacheFuture::pending(name, self.resolver.resolve(name),
+            self.ttl, &self.address_ttl_by, self.negative_ttl,
+            &self.addresses)
+    }
+}
+
+impl<R: HostResolve> HostResolve for CachingResolver<R> {
+    type HostFuture = CacheFuture<R::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        if let Some(value) = self.hosts.lock()
+            .expect("cache is not poisoned").get(name)
+        {
+            return CacheFuture::cached(name, value, &self.hosts);
+        }
+        CacheFuture::pending(name, self.resolver.resolve_host(name),
+            self.ttl, &self.host_ttl_by, self.negative_ttl, &self.hosts)
+    }
+}
+
+impl<R: Subscribe> Subscribe for CachingResolver<R> {
+    type Stream = R::Stream;
+    type Error = R::Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        self.resolver.subscribe(name)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for CachingResolver<R> {
+    type HostStream = R::HostStream;
+    type HostError = R::HostError;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.resolver.subscribe_host(name)
+    }
+}
+
+impl<F: Future<Error=Error>> CacheFuture<F>
+    where F::Item: Clone,
+{
+    fn cached(name: &Name, value: Option<F::Item>,
+              cache: &Arc<Mutex<Cache<F::Item>>>)
+        -> CacheFuture<F>
+    {
+        CacheFuture {
+            cached: Some(value.ok_or(Error::NameNotFound)),
+            future: None,
+            name: name.clone(),
+            ttl: Duration::new(0, 0),
+            ttl_by: None,
+            negative_ttl: Duration::new(0, 0),
+            cache: cache.clone(),
+        }
+    }
+    fn pending(name: &Name, future: F,
+               ttl: Duration, ttl_by: &Option<TtlCallback<F::Item>>,
+               negative_ttl: Duration, cache: &Arc<Mutex<Cache<F::Item>>>)
+        -> CacheFuture<F>
+    {
+        CacheFuture {
+            cached: None,
+            future: Some(future),
+            name: name.clone(),
+            ttl,
+            ttl_by: ttl_by.clone(),
+            negative_ttl,
+            cache: cache.clone(),
+        }
+    }
+}
+
+impl<F: Future<Error=Error>> Future for CacheFuture<F>
+    where F::Item: Clone,
+{
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, Error> {
+        if let Some(result) = self.cached.take() {
+            return result.map(Async::Ready);
+        }
+        let result = self.future.as_mut().expect("future polled twice").poll();
+        let (value, ttl) = match result {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(ref value)) => {
+                let ttl = self.ttl_by.as_ref()
+                    .and_then(|f| f(&self.name, value))
+                    .unwrap_or(self.ttl);
+                (Some(value.clone()), ttl)
+            }
+            Err(Error::NameNotFound) => (None, self.negative_ttl),
+            Err(_) => return result,
+        };
+        self.future = None;
+        self.cache.lock().expect("cache is not poisoned")
+            .insert(&self.name, value, ttl);
+        result
+    }
+}
+
+impl<T: Clone> Cache<T> {
+    fn new(capacity: usize) -> Cache<T> {
+        Cache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+    /// Returns `Some(None)` for the cached `NameNotFound`
+    fn get(&mut self, name: &Name) -> Option<Option<T>> {
+        let value = match self.entries.get_mut(name) {
+            Some(entry) if entry.expires > Instant::now() => {
+                self.order.remove(&entry.tick);
+                self.order.insert(self.tick, name.clone());
+                entry.tick = self.tick;
+                entry.value.clone()
+            }
+            Some(_) => {
+                self.remove(name);
+                return None;
+            }
+            None => return None,
+        };
+        self.tick += 1;
+        Some(value)
+    }
+    fn insert(&mut self, name: &Name, value: Option<T>, ttl: Duration) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(name);
+        if ttl == Duration::new(0, 0) {
+            return;
+        }
+        let expires = Instant::now() + ttl;
+        while self.entries.len() >= self.capacity {
+            let oldest = *self.order.keys().next()
+                .expect("order matches entries");
+            let name = self.order.remove(&oldest)
+                .expect("order matches entries");
+            self.entries.remove(&name);
+        }
+        self.order.insert(self.tick, name.clone());
+        self.entries.insert(name.clone(), Entry {
+            value,
+            expires,
+            tick: self.tick,
+        });
+        self.tick += 1;
+    }
+    fn remove(&mut self, name: &Name) {
+        if let Some(entry) = self.entries.remove(name) {
+            self.order.remove(&entry.tick);
+        }
+    }
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures::Future;
+    use futures::future::{FutureResult, ok, err};
+    use {HostResolve, Name, IpList, Error};
+    use super::CachingResolver;
+
+    #[derive(Debug)]
+    struct Counter(AtomicUsize);
+
+    impl HostResolve for Counter {
+        type HostFuture = FutureResult<IpList, Error>;
+        fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            if name.as_ref() == "missing" {
+                err(Error::NameNotFound)
+            } else if name.as_ref() == "short" {
+                ok(IpList::parse_list(&["127.0.0.2"]).unwrap())
+            } else {
+                ok(IpList::parse_list(&["127.0.0.1"]).unwrap())
+            }
+        }
+    }
+
+    fn resolve(cache: &CachingResolver<Counter>, name: &str)
+        -> Result<IpList, Error>
+    {
+        cache.resolve_host(&name.parse().unwrap()).wait()
+    }
+
+    fn calls(cache: &CachingResolver<Counter>) -> usize {
+        cache.resolver.0.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn positive() {
+        let cache = CachingResolver::new(Counter(AtomicUsize::new(0)), 10);
+        assert_eq!(resolve(&cache, "localhost").unwrap(),
+                   IpList::parse_list(&["127.0.0.1"]).unwrap());
+        assert_eq!(resolve(&cache, "localhost").unwrap(),
+                   IpList::parse_list(&["127.0.0.1"]).unwrap());
+        assert_eq!(calls(&cache), 1);
+        resolve(&cache, "example.org").unwrap();
+        assert_eq!(calls(&cache), 2);
+    }
+
+    #[test]
+    fn negative() {
+        let cache = CachingResolver::new(Counter(AtomicUsize::new(0)), 10);
+        match resolve(&cache, "missing") {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match resolve(&cache, "missing") {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(calls(&cache), 1);
+    }
+
+    #[test]
+    fn expire() {
+        let mut cache = CachingResolver::new(
+            Counter(AtomicUsize::new(0)), 10);
+        cache.ttl(Duration::new(0, 0)).negative_ttl(Duration::new(0, 0));
+        resolve(&cache, "localhost").unwrap();
+        resolve(&cache, "localhost").unwrap();
+        resolve(&cache, "missing").unwrap_err();
+        resolve(&cache, "missing").unwrap_err();
+        assert_eq!(calls(&cache), 4);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = CachingResolver::new(Counter(AtomicUsize::new(0)), 2);
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "c").unwrap();  // evicts "b"
+        assert_eq!(calls(&cache), 3);
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "c").unwrap();
+        assert_eq!(calls(&cache), 3);
+        resolve(&cache, "b").unwrap();
+        assert_eq!(calls(&cache), 4);
+    }
+
+    #[test]
+    fn evict_after_reads() {
+        let cache = CachingResolver::new(Counter(AtomicUsize::new(0)), 3);
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        resolve(&cache, "c").unwrap();
+        // every entry is just read, in the order different from insertion
+        resolve(&cache, "c").unwrap();
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        assert_eq!(calls(&cache), 3);
+        resolve(&cache, "d").unwrap();  // evicts "c"
+        resolve(&cache, "e").unwrap();  // evicts "a"
+        assert_eq!(calls(&cache), 5);
+        resolve(&cache, "b").unwrap();
+        resolve(&cache, "d").unwrap();
+        resolve(&cache, "e").unwrap();
+        assert_eq!(calls(&cache), 5);
+        resolve(&cache, "c").unwrap();  // evicts "b"
+        resolve(&cache, "a").unwrap();  // evicts "d"
+        assert_eq!(calls(&cache), 7);
+        resolve(&cache, "e").unwrap();
+        assert_eq!(calls(&cache), 7);
+    }
+
+    #[test]
+    fn ttl_by() {
+        let mut cache = CachingResolver::new(
+            Counter(AtomicUsize::new(0)), 10);
+        let short = IpList::parse_list(&["127.0.0.2"]).unwrap();
+        cache.host_ttl_by(move |_, ips| if *ips == short {
+            Some(Duration::new(0, 0))
+        } else {
+            None
+        });
+        resolve(&cache, "short").unwrap();
+        resolve(&cache, "short").unwrap();
+        resolve(&cache, "long").unwrap();
+        resolve(&cache, "long").unwrap();
+        assert_eq!(calls(&cache), 3);
+    }
+
+    #[test]
+    fn zero_negative_ttl_does_not_evict() {
+        let mut cache = CachingResolver::new(
+            Counter(AtomicUsize::new(0)), 1);
+        cache.negative_ttl(Duration::new(0, 0));
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "missing").unwrap_err();
+        resolve(&cache, "a").unwrap();
+        assert_eq!(calls(&cache), 2);
+    }
+
+    #[test]
+    fn invalidate() {
+        let cache = CachingResolver::new(Counter(AtomicUsize::new(0)), 10);
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        cache.invalidate(&"a".parse().unwrap());
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        assert_eq!(calls(&cache), 3);
+        cache.flush();
+        resolve(&cache, "a").unwrap();
+        resolve(&cache, "b").unwrap();
+        assert_eq!(calls(&cache), 5);
+    }
+}
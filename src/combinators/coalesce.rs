@@ -0,0 +1,198 @@
+use std::fmt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::{Async, Future};
+use futures::future::Shared;
+use {Name, Address, IpList, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// A resolver that shares a single in-flight request between all
+/// concurrent requests for the same name
+///
+/// When a name is requested while the previous request for the same name
+/// is still in progress, no new request is sent to the underlying resolver,
+/// but the result of the in-progress one is returned instead. Every caller
+/// gets its own copy of the address or error.
+///
+/// Futures of the underlying resolver must be `Send`, as they are boxed
+/// to be shared between callers. Subscriptions are passed to the
+/// underlying resolver as is.
+///
+/// Cloned resolver shares the in-flight requests with the original one.
+#[derive(Debug, Clone)]
+pub struct CoalescingResolver<R> {
+    resolver: R,
+    addresses: Arc<Mutex<InFlight<Address>>>,
+    hosts: Arc<Mutex<InFlight<IpList>>>,
+}
+
+type BoxFuture<T> = Box<dyn Future<Item=T, Error=Error> + Send>;
+
+/// A future returned by `CoalescingResolver`
+pub struct CoalesceFuture<T> {
+    future: Shared<BoxFuture<T>>,
+    name: Name,
+    id: u64,
+    in_flight: Arc<Mutex<InFlight<T>>>,
+}
+
+struct InFlight<T> {
+    next_id: u64,
+    futures: HashMap<Name, Entry<T>>,
+}
+
+struct Entry<T> {
+    id: u64,
+    future: Shared<BoxFuture<T>>,
+    /// Number of `CoalesceFuture`s waiting for this future
+    waiters: usize,
+}
+
+impl<R> CoalescingResolver<R> {
+    /// Create a resolver that coalesces concurrent requests
+    pub fn new(resolver: R) -> CoalescingResolver<R> {
+        CoalescingResolver {
+            resolver,
+            addresses: Arc::new(Mutex::new(InFlight::new())),
+            hosts: Arc::new(Mutex::new(InFlight::new())),
+        }
+    }
+}
+
+impl<T> InFlight<T> {
+    fn new() -> InFlight<T> {
+        InFlight {
+            next_id: 0,
+            futures: HashMap::new(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for InFlight<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InFlight")
+        .field("names", &self.futures.keys().collect::<Vec<_>>())
+        .finish()
+    }
+}
+
+impl<T> fmt::Debug for CoalesceFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CoalesceFuture")
+        .field("name", &self.name)
+        .finish()
+    }
+}
+
+impl<T> CoalesceFuture<T> {
+    fn new<F, G>(in_flight: &Arc<Mutex<InFlight<T>>>, name: &Name, resolve: G)
+        -> CoalesceFuture<T>
+        where G: FnOnce() -> F,
+              F: Future<Item=T, Error=Error> + Send + 'static,
+    {
+        let mut lock = in_flight.lock().expect("in-flight is not poisoned");
+        let existing = lock.futures.get_mut(name).map(|entry| {
+            entry.waiters += 1;
+            (entry.id, entry.future.clone())
+        });
+        let (id, future) = match existing {
+            Some(pair) => pair,
+            None => {
+                let id = lock.next_id;
+                let future = (Box::new(resolve()) as BoxFuture<T>).shared();
+                lock.next_id += 1;
+                lock.futures.insert(name.clone(), Entry {
+                    id,
+                    future: future.clone(),
+                    waiters: 1,
+                });
+                (id, future)
+            }
+        };
+        CoalesceFuture {
+            future,
+            name: name.clone(),
+            id,
+            in_flight: in_flight.clone(),
+        }
+    }
+}
+
+impl<R: Resolve> Resolve for CoalescingResolver<R>
+    where R::Future: Send + 'static,
+{
+    type Future = CoalesceFuture<Address>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        CoalesceFuture::new(&self.addresses, name,
+                            || self.resolver.resolve(name))
+    }
+}
+
+impl<R: HostResolve> HostResolve for CoalescingResolver<R>
+    where R::HostFuture: Send + 'static,
+{
+    type HostFuture = CoalesceFuture<IpList>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        CoalesceFuture::new(&self.hosts, name,
+                            || self.resolver.resolve_host(name))
+    }
+}
+
+impl<R: Subscribe> Subscribe for CoalescingResolver<R> {
+    type Stream = R::Stream;
+    type Error = R::Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        self.resolver.subscribe(name)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for CoalescingResolver<R> {
+    type HostStream = R::HostStream;
+    type HostError = R::HostError;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.resolver.subscribe_host(name)
+    }
+}
+
+impl<T: Clone> Future for CoalesceFuture<T> {
+    type Item = T;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<T>, Error> {
+        let result = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(value)) => Ok(Async::Ready((*value).clone())),
+            Err(e) => Err(e.into()),
+        };
+        let mut lock = self.in_flight.lock()
+            .expect("in-flight is not poisoned");
+        // the entry may already be replaced by a newer request
+        if lock.futures.get(&self.name).map(|e| e.id) == Some(self.id) {
+            lock.futures.remove(&self.name);
+        }
+        result
+    }
+}
+
+impl<T> Drop for CoalesceFuture<T> {
+    fn drop(&mut self) {
+        // if all waiters are gone before completion (e.g. cancelled by
+        // timeout), nobody drives the shared future, so forget it and
+        // start a new request next time
+        let mut lock = match self.in_flight.lock() {
+            Ok(lock) => lock,
+            Err(_) => return,
+        };
+        let remove = match lock.futures.get_mut(&self.name) {
+            Some(ref mut entry) if entry.id == self.id => {
+                entry.waiters -= 1;
+                entry.waiters == 0
+            }
+            _ => false,
+        };
+        if remove {
+            lock.futures.remove(&self.name);
+        }
+    }
+}
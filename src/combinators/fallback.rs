@@ -0,0 +1,362 @@
+use std::sync::Arc;
+
+use futures::{Async, Future, Stream};
+use {Name, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// A resolver that tries the primary resolver first and uses a fallback one
+/// if the name is not found by the primary resolver
+///
+/// By default only `NameNotFound` (and `NoDefaultPort`) errors make
+/// resolver try the fallback. Use `fallback_on_temporary` to also try
+/// the fallback resolver when the primary one fails with `TemporaryError`
+/// or `Timeout`. Other errors (i.e. `InvalidName`) are returned as is.
+///
+/// Subscriptions switch to the fallback resolver when the primary stream
+/// fails with the same kind of errors.
+///
+/// Fallback resolvers can be nested to make a chain of resolvers of
+/// different types, or use `FallbackChain` for the list of resolvers
+/// of the same type.
+#[derive(Debug)]
+pub struct Fallback<A, B> {
+    primary: A,
+    fallback: Arc<B>,
+    on_temporary: bool,
+}
+
+/// A resolver that tries each resolver in the list until the name is found
+///
+/// This is a version of `Fallback` for any number of resolvers of the same
+/// type (see there for the rules of switching to the next resolver).
+/// Chain of zero resolvers returns `NameNotFound` for every name.
+#[derive(Debug)]
+pub struct FallbackChain<R> {
+    resolvers: Arc<Vec<R>>,
+    on_temporary: bool,
+}
+
+/// A future returned by `Fallback` resolver
+#[derive(Debug)]
+pub struct FallbackFuture<F, R, G> {
+    primary: Option<F>,
+    fallback: Option<G>,
+    resolver: Arc<R>,
+    resolve: fn(&R, &Name) -> G,
+    name: Name,
+    on_temporary: bool,
+}
+
+/// A stream returned by `Fallback` subscriber
+#[derive(Debug)]
+pub struct FallbackStream<S, R, T> {
+    primary: Option<S>,
+    fallback: Option<T>,
+    resolver: Arc<R>,
+    subscribe: fn(&R, &Name) -> T,
+    name: Name,
+    on_temporary: bool,
+}
+
+/// A future returned by `FallbackChain` resolver
+#[derive(Debug)]
+pub struct ChainFuture<R, F> {
+    future: Option<F>,
+    index: usize,
+    resolvers: Arc<Vec<R>>,
+    resolve: fn(&R, &Name) -> F,
+    name: Name,
+    on_temporary: bool,
+}
+
+/// A stream returned by `FallbackChain` subscriber
+#[derive(Debug)]
+pub struct ChainStream<R, S> {
+    stream: Option<S>,
+    index: usize,
+    resolvers: Arc<Vec<R>>,
+    subscribe: fn(&R, &Name) -> S,
+    name: Name,
+    on_temporary: bool,
+}
+
+fn should_fallback(err: &Error, on_temporary: bool) -> bool {
+    match *err {
+        Error::NameNotFound | Error::NoDefaultPort => true,
+        Error::TemporaryError(_) | Error::Timeout => on_temporary,
+        _ => false,
+    }
+}
+
+impl<A, B> Fallback<A, B> {
+    /// Create a resolver that uses `fallback` if `primary` can't resolve
+    /// the name
+    pub fn new(primary: A, fallback: B) -> Fallback<A, B> {
+        Fallback {
+            primary,
+            fallback: Arc::new(fallback),
+            on_temporary: false,
+        }
+    }
+    /// Also use the fallback resolver when primary one returns
+    /// `TemporaryError` or `Timeout`
+    pub fn fallback_on_temporary(&mut self, value: bool) -> &mut Self {
+        self.on_temporary = value;
+        self
+    }
+}
+
+impl<R> FallbackChain<R> {
+    /// Create a resolver that tries resolvers one by one
+    pub fn new(resolvers: Vec<R>) -> FallbackChain<R> {
+        FallbackChain {
+            resolvers: Arc::new(resolvers),
+            on_temporary: false,
+        }
+    }
+    /// Also use the next resolver when previous one returns
+    /// `TemporaryError` or `Timeout`
+    pub fn fallback_on_temporary(&mut self, value: bool) -> &mut Self {
+        self.on_temporary = value;
+        self
+    }
+}
+
+impl<A: Resolve, B: Resolve> Resolve for Fallback<A, B> {
+    type Future = FallbackFuture<A::Future, B, B::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        FallbackFuture {
+            primary: Some(self.primary.resolve(name)),
+            fallback: None,
+            resolver: self.fallback.clone(),
+            resolve: B::resolve,
+            name: name.clone(),
+            on_temporary: self.on_temporary,
+        }
+    }
+}
+
+impl<A: HostResolve, B: HostResolve> HostResolve for Fallback<A, B> {
+    type HostFuture = FallbackFuture<A::HostFuture, B, B::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        FallbackFuture {
+            primary: Some(self.primary.resolve_host(name)),
+            fallback: None,
+            resolver: self.fallback.clone(),
+            resolve: B::resolve_host,
+            name: name.clone(),
+            on_temporary: self.on_temporary,
+        }
+    }
+}
+
+impl<A: Subscribe, B: Subscribe> Subscribe for Fallback<A, B> {
+    type Stream = FallbackStream<A::Stream, B, B::Stream>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        FallbackStream {
+            primary: Some(self.primary.subscribe(name)),
+            fallback: None,
+            resolver: self.fallback.clone(),
+            subscribe: B::subscribe,
+            name: name.clone(),
+            on_temporary: self.on_temporary,
+        }
+    }
+}
+
+impl<A: HostSubscribe, B: HostSubscribe> HostSubscribe for Fallback<A, B> {
+    type HostStream = FallbackStream<A::HostStream, B, B::HostStream>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        FallbackStream {
+            primary: Some(self.primary.subscribe_host(name)),
+            fallback: None,
+            resolver: self.fallback.clone(),
+            subscribe: B::subscribe_host,
+            name: name.clone(),
+            on_temporary: self.on_temporary,
+        }
+    }
+}
+
+impl<R: Resolve> Resolve for FallbackChain<R> {
+    type Future = ChainFuture<R, R::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        ChainFuture::new(&self.resolvers, R::resolve, name, self.on_temporary)
+    }
+}
+
+impl<R: HostResolve> HostResolve for FallbackChain<R> {
+    type HostFuture = ChainFuture<R, R::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        ChainFuture::new(&self.resolvers, R::resolve_host,
+                         name, self.on_temporary)
+    }
+}
+
+impl<R: Subscribe> Subscribe for FallbackChain<R> {
+    type Stream = ChainStream<R, R::Stream>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        ChainStream::new(&self.resolvers, R::subscribe,
+                         name, self.on_temporary)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for FallbackChain<R> {
+    type HostStream = ChainStream<R, R::HostStream>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        ChainStream::new(&self.resolvers, R::subscribe_host,
+                         name, self.on_temporary)
+    }
+}
+
+impl<F, R, G> Future for FallbackFuture<F, R, G>
+    where F: Future<Error=Error>,
+          G: Future<Item=F::Item, Error=Error>,
+{
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, Error> {
+        if let Some(mut primary) = self.primary.take() {
+            match primary.poll() {
+                Ok(Async::NotReady) => {
+                    self.primary = Some(primary);
+                    return Ok(Async::NotReady);
+                }
+                Ok(Async::Ready(value)) => return Ok(Async::Ready(value)),
+                Err(ref e) if should_fallback(e, self.on_temporary) => {
+                    self.fallback = Some(
+                        (self.resolve)(&self.resolver, &self.name));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.fallback.as_mut().expect("future polled twice").poll()
+    }
+}
+
+impl<S, R, T> Stream for FallbackStream<S, R, T>
+    where S: Stream,
+          S::Error: Into<Error>,
+          T: Stream<Item=S::Item>,
+          T::Error: Into<Error>,
+{
+    type Item = S::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, Error> {
+        if let Some(mut primary) = self.primary.take() {
+            match primary.poll().map_err(|e| e.into()) {
+                Ok(value) => {
+                    self.primary = Some(primary);
+                    return Ok(value);
+                }
+                Err(ref e) if should_fallback(e, self.on_temporary) => {
+                    self.fallback = Some(
+                        (self.subscribe)(&self.resolver, &self.name));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.fallback.as_mut().expect("stream polled after error")
+            .poll().map_err(|e| e.into())
+    }
+}
+
+impl<R, F: Future<Error=Error>> ChainFuture<R, F> {
+    fn new(resolvers: &Arc<Vec<R>>, resolve: fn(&R, &Name) -> F,
+           name: &Name, on_temporary: bool)
+        -> ChainFuture<R, F>
+    {
+        ChainFuture {
+            future: resolvers.first().map(|r| resolve(r, name)),
+            index: 0,
+            resolvers: resolvers.clone(),
+            resolve,
+            name: name.clone(),
+            on_temporary,
+        }
+    }
+}
+
+impl<R, F: Future<Error=Error>> Future for ChainFuture<R, F> {
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, Error> {
+        loop {
+            let result = match self.future.as_mut() {
+                Some(future) => future.poll(),
+                None => return Err(Error::NameNotFound),
+            };
+            match result {
+                Err(ref e) if should_fallback(e, self.on_temporary) => {
+                    self.index += 1;
+                    match self.resolvers.get(self.index) {
+                        Some(r) => {
+                            self.future = Some((self.resolve)(r, &self.name));
+                        }
+                        None => {
+                            self.future = None;
+                            return result;
+                        }
+                    }
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+impl<R, S> ChainStream<R, S>
+    where S: Stream,
+          S::Error: Into<Error>,
+{
+    fn new(resolvers: &Arc<Vec<R>>, subscribe: fn(&R, &Name) -> S,
+           name: &Name, on_temporary: bool)
+        -> ChainStream<R, S>
+    {
+        ChainStream {
+            stream: resolvers.first().map(|r| subscribe(r, name)),
+            index: 0,
+            resolvers: resolvers.clone(),
+            subscribe,
+            name: name.clone(),
+            on_temporary,
+        }
+    }
+}
+
+impl<R, S> Stream for ChainStream<R, S>
+    where S: Stream,
+          S::Error: Into<Error>,
+{
+    type Item = S::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, Error> {
+        loop {
+            let result = match self.stream.as_mut() {
+                Some(stream) => stream.poll().map_err(|e| e.into()),
+                None => return Err(Error::NameNotFound),
+            };
+            match result {
+                Err(ref e) if should_fallback(e, self.on_temporary) => {
+                    self.index += 1;
+                    match self.resolvers.get(self.index) {
+                        Some(r) => {
+                            self.stream = Some(
+                                (self.subscribe)(r, &self.name));
+                        }
+                        None => {
+                            self.stream = None;
+                            return result;
+                        }
+                    }
+                }
+                _ => return result,
+            }
+        }
+    }
+}
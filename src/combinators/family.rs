@@ -0,0 +1,287 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use futures::{Async, Future, Stream};
+use addr::Builder;
+use {Name, Address, IpList};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// Defines which address families are returned by `FamilyFilter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FamilyPolicy {
+    /// Only IPv4 addresses are kept
+    Ipv4Only,
+    /// Only IPv6 addresses are kept
+    Ipv6Only,
+    /// Both families are kept, but IPv6 addresses go first
+    ///
+    /// For `IpList` this means IPv6 addresses are moved to the start of
+    /// the list. For `Address` every priority set is split into two: IPv6
+    /// addresses with their original priority and IPv4 addresses with
+    /// slightly lower priority, so IPv4 addresses are only used when IPv6
+    /// ones are not accessible.
+    PreferIpv6,
+    /// Both families are kept, but IPv4 addresses go first
+    PreferIpv4,
+    /// Addresses of a family are dropped if host has no configured
+    /// address of that family (like `AI_ADDRCONFIG` in `getaddrinfo`)
+    ///
+    /// Address of the family is considered configured if the system can
+    /// select a non-loopback source address to reach a host of that
+    /// family. This is checked every time name is resolved (or updated).
+    AddrConfig,
+}
+
+/// A resolver that filters or reorders addresses by their family
+///
+/// This is useful when host doesn't have IPv6 (or IPv4) connectivity, so
+/// addresses of that family would only slow down connection. Note: if all
+/// addresses are filtered out, empty address is returned rather than
+/// an error.
+#[derive(Debug)]
+pub struct FamilyFilter<R> {
+    resolver: R,
+    policy: FamilyPolicy,
+}
+
+/// A future returned by `FamilyFilter` resolver
+#[derive(Debug)]
+pub struct FamilyFuture<F: Future> {
+    future: F,
+    policy: FamilyPolicy,
+    apply: fn(&FamilyPolicy, &F::Item) -> F::Item,
+}
+
+/// A stream returned by `FamilyFilter` subscriber
+///
+/// Value is skipped if it's the same as the previous one after filtering.
+#[derive(Debug)]
+pub struct FamilyStream<S: Stream> {
+    stream: S,
+    policy: FamilyPolicy,
+    apply: fn(&FamilyPolicy, &S::Item) -> S::Item,
+    last_value: Option<S::Item>,
+}
+
+/// Returns true if system has a route to the address of the same family
+/// as `probe` using non-loopback source address
+///
+/// Probe address is from a documentation range and nothing is sent.
+fn has_source(probe: IpAddr) -> bool {
+    let bind: IpAddr = match probe {
+        IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    UdpSocket::bind(SocketAddr::new(bind, 0))
+        .and_then(|sock| {
+            sock.connect(SocketAddr::new(probe, 9))?;
+            sock.local_addr()
+        })
+        .map(|addr| !addr.ip().is_loopback() && !addr.ip().is_unspecified())
+        .unwrap_or(false)
+}
+
+impl FamilyPolicy {
+    /// Returns `(ipv4, ipv6)` pair of flags denoting whether addresses of
+    /// each family are kept
+    fn families(&self) -> (bool, bool) {
+        match *self {
+            FamilyPolicy::Ipv4Only => (true, false),
+            FamilyPolicy::Ipv6Only => (false, true),
+            FamilyPolicy::PreferIpv6 | FamilyPolicy::PreferIpv4 => {
+                (true, true)
+            }
+            FamilyPolicy::AddrConfig => {
+                (has_source(Ipv4Addr::new(192, 0, 2, 1).into()),
+                 has_source(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+                            .into()))
+            }
+        }
+    }
+    /// Returns `Some(true)` if IPv6 goes first, `Some(false)` if IPv4 does
+    fn preferred_ipv6(&self) -> Option<bool> {
+        match *self {
+            FamilyPolicy::PreferIpv6 => Some(true),
+            FamilyPolicy::PreferIpv4 => Some(false),
+            _ => None,
+        }
+    }
+    /// Apply policy to a list of IP addresses
+    pub fn apply_ips(&self, ips: &IpList) -> IpList {
+        let (v4, v6) = self.families();
+        let mut result = ips.iter().cloned()
+            .filter(|ip| if ip.is_ipv4() { v4 } else { v6 })
+            .collect::<Vec<_>>();
+        if let Some(ipv6) = self.preferred_ipv6() {
+            // stable sort keeps original order within the family
+            result.sort_by_key(|ip| ip.is_ipv6() != ipv6);
+        }
+        result.into()
+    }
+    /// Apply policy to an address
+    pub fn apply(&self, address: &Address) -> Address {
+        let (v4, v6) = self.families();
+        let mut builder = Builder::new();
+        for set in address.iter() {
+            let (ipv6, ipv4): (Vec<_>, Vec<_>) = set.entries().iter()
+                .filter(|&&(_, a)| if a.is_ipv4() { v4 } else { v6 })
+                .partition(|&&(_, a)| a.is_ipv6());
+            match self.preferred_ipv6() {
+                Some(true) => {
+                    builder.add_addresses(&ipv6);
+                    builder.add_addresses(&ipv4);
+                }
+                Some(false) => {
+                    builder.add_addresses(&ipv4);
+                    builder.add_addresses(&ipv6);
+                }
+                None => {
+                    builder.add_addresses(ipv6.iter().chain(&ipv4));
+                }
+            }
+        }
+        builder.into_address()
+    }
+}
+
+impl<R> FamilyFilter<R> {
+    /// Create a resolver that applies `policy` to every resolved address
+    pub fn new(resolver: R, policy: FamilyPolicy) -> FamilyFilter<R> {
+        FamilyFilter { resolver, policy }
+    }
+}
+
+impl<R: Resolve> Resolve for FamilyFilter<R> {
+    type Future = FamilyFuture<R::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        FamilyFuture {
+            future: self.resolver.resolve(name),
+            policy: self.policy,
+            apply: FamilyPolicy::apply,
+        }
+    }
+}
+
+impl<R: HostResolve> HostResolve for FamilyFilter<R> {
+    type HostFuture = FamilyFuture<R::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        FamilyFuture {
+            future: self.resolver.resolve_host(name),
+            policy: self.policy,
+            apply: FamilyPolicy::apply_ips,
+        }
+    }
+}
+
+impl<R: Subscribe> Subscribe for FamilyFilter<R> {
+    type Stream = FamilyStream<R::Stream>;
+    type Error = R::Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        FamilyStream {
+            stream: self.resolver.subscribe(name),
+            policy: self.policy,
+            apply: FamilyPolicy::apply,
+            last_value: None,
+        }
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for FamilyFilter<R> {
+    type HostStream = FamilyStream<R::HostStream>;
+    type HostError = R::HostError;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        FamilyStream {
+            stream: self.resolver.subscribe_host(name),
+            policy: self.policy,
+            apply: FamilyPolicy::apply_ips,
+            last_value: None,
+        }
+    }
+}
+
+impl<F: Future> Future for FamilyFuture<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, F::Error> {
+        match self.future.poll()? {
+            Async::Ready(value) => {
+                Ok(Async::Ready((self.apply)(&self.policy, &value)))
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<S> Stream for FamilyStream<S>
+    where S: Stream,
+          S::Item: PartialEq + Clone,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, S::Error> {
+        loop {
+            match self.stream.poll()? {
+                Async::Ready(Some(value)) => {
+                    let value = (self.apply)(&self.policy, &value);
+                    if self.last_value.as_ref() != Some(&value) {
+                        self.last_value = Some(value.clone());
+                        return Ok(Async::Ready(Some(value)));
+                    }
+                }
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use addr::Builder;
+    use {Address, IpList};
+    use super::FamilyPolicy;
+
+    fn ips() -> IpList {
+        IpList::parse_list(&["127.0.0.1", "::1", "127.0.0.2", "::2"])
+            .unwrap()
+    }
+
+    fn address() -> Address {
+        let mut builder = Builder::new();
+        builder.add_addresses(&[(1, "127.0.0.1:80".parse().unwrap()),
+                                (2, "[::1]:80".parse().unwrap())]);
+        builder.add_addresses(&[(3, "[::2]:80".parse().unwrap())]);
+        builder.into_address()
+    }
+
+    #[test]
+    fn ip_list() {
+        let list = |x: &[&str]| IpList::parse_list(x).unwrap();
+        assert_eq!(FamilyPolicy::Ipv4Only.apply_ips(&ips()),
+                   list(&["127.0.0.1", "127.0.0.2"]));
+        assert_eq!(FamilyPolicy::Ipv6Only.apply_ips(&ips()),
+                   list(&["::1", "::2"]));
+        assert_eq!(FamilyPolicy::PreferIpv6.apply_ips(&ips()),
+                   list(&["::1", "::2", "127.0.0.1", "127.0.0.2"]));
+        assert_eq!(FamilyPolicy::PreferIpv4.apply_ips(&ips()),
+                   list(&["127.0.0.1", "127.0.0.2", "::1", "::2"]));
+    }
+
+    #[test]
+    fn address_only() {
+        assert_eq!(FamilyPolicy::Ipv4Only.apply(&address()),
+                   "[127.0.0.1:80 w=1]".parse().unwrap());
+        assert_eq!(FamilyPolicy::Ipv6Only.apply(&address()),
+                   "[[::1]:80 w=2] / [[::2]:80 w=3]".parse().unwrap());
+    }
+
+    #[test]
+    fn address_prefer() {
+        assert_eq!(FamilyPolicy::PreferIpv6.apply(&address()),
+            "[[::1]:80 w=2] / [127.0.0.1:80 w=1] / [[::2]:80 w=3]"
+            .parse().unwrap());
+        assert_eq!(FamilyPolicy::PreferIpv4.apply(&address()),
+            "[127.0.0.1:80 w=1] / [[::1]:80 w=2] / [[::2]:80 w=3]"
+            .parse().unwrap());
+    }
+}
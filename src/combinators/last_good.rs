@@ -0,0 +1,170 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Stream};
+use tokio_timer::Delay;
+use void::Void;
+use {Name, Error};
+use {Subscribe, HostSubscribe};
+
+
+type ErrorCallback = Arc<dyn Fn(&Name, &Error) + Send + Sync>;
+
+/// A subscriber that never fails and keeps the last good value
+///
+/// Streams of this subscriber have `Void` as the error type, so they can be
+/// passed directly to connection pools. When the underlying stream fails,
+/// the error is reported to a callback (see `on_error`) and the name is
+/// subscribed again after `resubscribe_delay` (one second by default).
+/// Meanwhile consumer keeps using the last address yielded. The value
+/// received after resubscription is yielded only if it differs from the
+/// last one.
+///
+/// The end of the underlying stream is passed through, as it means that
+/// consumer should be shut down. The stream also ends if there is no
+/// timer to wait before resubscribing (i.e. not in a tokio runtime).
+#[derive(Clone)]
+pub struct LastKnownGood<R> {
+    resolver: Arc<R>,
+    on_error: ErrorCallback,
+    delay: Duration,
+}
+
+/// A stream returned by `LastKnownGood` subscriber
+pub struct LastKnownGoodStream<R, S: Stream> {
+    resolver: Arc<R>,
+    subscribe: fn(&R, &Name) -> S,
+    name: Name,
+    on_error: ErrorCallback,
+    delay: Duration,
+    stream: Option<S>,
+    timer: Option<Delay>,
+    last_value: Option<S::Item>,
+}
+
+impl<R> LastKnownGood<R> {
+    /// Create a subscriber which ignores errors
+    pub fn new(resolver: R) -> LastKnownGood<R> {
+        LastKnownGood {
+            resolver: Arc::new(resolver),
+            on_error: Arc::new(|_, _| {}),
+            delay: Duration::new(1, 0),
+        }
+    }
+    /// Set a callback that is called on every error of the subscription
+    ///
+    /// This is a good place to log errors.
+    pub fn on_error<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&Name, &Error) + Send + Sync + 'static
+    {
+        self.on_error = Arc::new(f);
+        self
+    }
+    /// Set delay before subscribing again after an error
+    pub fn resubscribe_delay(&mut self, delay: Duration) -> &mut Self {
+        self.delay = delay;
+        self
+    }
+    fn stream<S: Stream>(&self, subscribe: fn(&R, &Name) -> S, name: &Name)
+        -> LastKnownGoodStream<R, S>
+    {
+        LastKnownGoodStream {
+            stream: Some(subscribe(&self.resolver, name)),
+            resolver: self.resolver.clone(),
+            subscribe,
+            name: name.clone(),
+            on_error: self.on_error.clone(),
+            delay: self.delay,
+            timer: None,
+            last_value: None,
+        }
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for LastKnownGood<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LastKnownGood")
+        .field("resolver", &self.resolver)
+        .field("resubscribe_delay", &self.delay)
+        .finish()
+    }
+}
+
+impl<R, S> fmt::Debug for LastKnownGoodStream<R, S>
+    where S: Stream + fmt::Debug,
+          S::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LastKnownGoodStream")
+        .field("name", &self.name)
+        .field("stream", &self.stream)
+        .field("timer", &self.timer)
+        .field("last_value", &self.last_value)
+        .finish()
+    }
+}
+
+impl<R: Subscribe> Subscribe for LastKnownGood<R> {
+    type Stream = LastKnownGoodStream<R, R::Stream>;
+    type Error = Void;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        self.stream(R::subscribe, name)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for LastKnownGood<R> {
+    type HostStream = LastKnownGoodStream<R, R::HostStream>;
+    type HostError = Void;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.stream(R::subscribe_host, name)
+    }
+}
+
+impl<R, S> Stream for LastKnownGoodStream<R, S>
+    where S: Stream,
+          S::Item: PartialEq + Clone,
+          S::Error: Into<Error>,
+{
+    type Item = S::Item;
+    type Error = Void;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, Void> {
+        loop {
+            if let Some(mut timer) = self.timer.take() {
+                match timer.poll() {
+                    Ok(Async::Ready(())) => {}
+                    Ok(Async::NotReady) => {
+                        self.timer = Some(timer);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => {
+                        // there is no way to wait without a timer
+                        (self.on_error)(&self.name,
+                            &Error::TemporaryError(Box::new(e)));
+                        return Ok(Async::Ready(None));
+                    }
+                }
+                self.stream = Some(
+                    (self.subscribe)(&self.resolver, &self.name));
+            }
+            let result = self.stream.as_mut()
+                .expect("either stream or timer is active")
+                .poll();
+            match result {
+                Ok(Async::Ready(Some(value))) => {
+                    if self.last_value.as_ref() != Some(&value) {
+                        self.last_value = Some(value.clone());
+                        return Ok(Async::Ready(Some(value)));
+                    }
+                }
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    (self.on_error)(&self.name, &e.into());
+                    self.stream = None;
+                    self.timer = Some(Delay::new(Instant::now() + self.delay));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::collections::HashMap;
+
+use futures::{Async, Stream};
+use addr::{merge, WeightMode};
+use {Name, Address, Error, Subscribe};
+
+
+type ErrorCallback = Box<dyn Fn(&Name, &Error) + Send + Sync>;
+
+
+/// A stream that subscribes to a changing list of names and yields the
+/// union of their addresses
+///
+/// Create it with `Subscribe::subscribe_many`.
+///
+/// Every name in the list has its own subscription. When a new list of
+/// names arrives, names which are not in the list anymore are unsubscribed
+/// and new names are subscribed. Merged address is yielded when all names
+/// from the list have got their first value (or failed) and after that
+/// whenever any address changes. Addresses are merged with `addr::merge`,
+/// so priorities are kept, and weights of the address found in multiple
+/// names are summed (see `weight_mode`).
+///
+/// If the subscription of a single name fails, the error is reported to
+/// the callback (see `on_error`) and addresses of this name are removed
+/// from the result, but the stream continues to work. The name is
+/// subscribed again when the next list containing it arrives. Wrap the
+/// resolver with `Retry` to retry temporary errors sooner.
+///
+/// The end of the stream of names is not the end of this stream, the last
+/// list of names is used until all its subscriptions end. Errors of the
+/// stream of names are forwarded though.
+pub struct SubscribeMany<R: Subscribe, S> {
+    resolver: R,
+    names: Option<S>,
+    members: HashMap<Name, Member<R::Stream>>,
+    last_value: Option<Address>,
+    changed: bool,
+    weights: WeightMode,
+    on_error: ErrorCallback,
+}
+
+#[derive(Debug)]
+struct Member<S> {
+    stream: Option<S>,
+    value: Option<Address>,
+    ready: bool,
+}
+
+impl<R: Subscribe, S> SubscribeMany<R, S> {
+    pub(crate) fn new(resolver: R, names: S) -> SubscribeMany<R, S> {
+        SubscribeMany {
+            resolver,
+            names: Some(names),
+            members: HashMap::new(),
+            last_value: None,
+            changed: false,
+            weights: WeightMode::Sum,
+            on_error: Box::new(|_, _| {}),
+        }
+    }
+    /// Set how weights of the same address in multiple names are combined
+    ///
+    /// Default is `WeightMode::Sum`. Use `WeightMode::Normalize` if some
+    /// names have zero weights and others don't.
+    pub fn weight_mode(&mut self, mode: WeightMode) -> &mut Self {
+        self.weights = mode;
+        self
+    }
+    /// Set a callback that is called when subscription of a name fails
+    ///
+    /// This is a good place to log errors.
+    pub fn on_error<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&Name, &Error) + Send + Sync + 'static
+    {
+        self.on_error = Box::new(f);
+        self
+    }
+    fn update_names(&mut self, names: Vec<Name>) {
+        let mut members = HashMap::with_capacity(names.len());
+        for name in names {
+            if members.contains_key(&name) {
+                continue;
+            }
+            let member = match self.members.remove(&name) {
+                // failed subscriptions are retried with the new list
+                Some(ref m) if m.stream.is_none() && m.value.is_none() => {
+                    self.subscribe(&name)
+                }
+                Some(member) => member,
+                None => self.subscribe(&name),
+            };
+            members.insert(name, member);
+        }
+        self.members = members;
+        self.changed = true;
+    }
+    fn subscribe(&self, name: &Name) -> Member<R::Stream> {
+        Member {
+            stream: Some(self.resolver.subscribe(name)),
+            value: None,
+            ready: false,
+        }
+    }
+}
+
+impl<R, S> fmt::Debug for SubscribeMany<R, S>
+    where R: Subscribe + fmt::Debug,
+          R::Stream: fmt::Debug,
+          S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SubscribeMany")
+        .field("resolver", &self.resolver)
+        .field("names", &self.names)
+        .field("members", &self.members)
+        .field("last_value", &self.last_value)
+        .field("weight_mode", &self.weights)
+        .finish()
+    }
+}
+
+impl<R, S> Stream for SubscribeMany<R, S>
+    where R: Subscribe,
+          S: Stream<Item=Vec<Name>>,
+          S::Error: Into<Error>,
+{
+    type Item = Address;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<Address>>, Error> {
+        while let Some(next) = self.names.as_mut().map(|s| s.poll()) {
+            match next.map_err(|e| e.into())? {
+                Async::Ready(Some(names)) => self.update_names(names),
+                Async::Ready(None) => self.names = None,
+                Async::NotReady => break,
+            }
+        }
+        for (name, member) in self.members.iter_mut() {
+            while let Some(result) = member.stream.as_mut().map(|s| s.poll()) {
+                match result {
+                    Ok(Async::Ready(Some(value))) => {
+                        member.value = Some(value);
+                        member.ready = true;
+                        self.changed = true;
+                    }
+                    Ok(Async::Ready(None)) => {
+                        member.stream = None;
+                        member.ready = true;
+                    }
+                    Ok(Async::NotReady) => break,
+                    Err(e) => {
+                        (self.on_error)(name, &e.into());
+                        member.stream = None;
+                        member.value = None;
+                        member.ready = true;
+                        self.changed = true;
+                    }
+                }
+            }
+        }
+        if self.changed && self.members.values().all(|m| m.ready) {
+            self.changed = false;
+            let value = merge(self.members.values()
+                .filter_map(|m| m.value.as_ref()), self.weights);
+            if self.last_value.as_ref() != Some(&value) {
+                self.last_value = Some(value.clone());
+                return Ok(Async::Ready(Some(value)));
+            }
+        }
+        if self.names.is_none() &&
+            self.members.values().all(|m| m.stream.is_none())
+        {
+            return Ok(Async::Ready(None));
+        }
+        Ok(Async::NotReady)
+    }
+}
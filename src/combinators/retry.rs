@@ -0,0 +1,305 @@
+use std::cmp::min;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Stream};
+use rand::{thread_rng, Rng};
+use tokio_timer::Delay;
+use {Name, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// A resolver that retries name resolution on temporary errors
+///
+/// Only `TemporaryError` and `Timeout` are retried. Delay between attempts
+/// grows exponentially from `initial_delay` up to the `max_delay`, the
+/// actual delay is randomized to be between half and a full value of the
+/// computed one, so that many clients don't retry at the same time.
+///
+/// Subscriptions are retried too: if the stream fails with a temporary
+/// error, the name is subscribed again after a delay instead of returning
+/// the error. Number of attempts is reset when a stream yields a value.
+///
+/// By default there are 5 attempts with the initial delay of 100
+/// milliseconds and the maximum delay of 10 seconds.
+#[derive(Debug)]
+pub struct Retry<R> {
+    resolver: Arc<R>,
+    backoff: Backoff,
+}
+
+/// A future returned by `Retry` resolver
+#[derive(Debug)]
+pub struct RetryFuture<R, F> {
+    resolver: Arc<R>,
+    resolve: fn(&R, &Name) -> F,
+    name: Name,
+    backoff: Backoff,
+    attempt: u32,
+    future: Option<F>,
+    timer: Option<Delay>,
+}
+
+/// A stream returned by `Retry` subscriber
+#[derive(Debug)]
+pub struct RetryStream<R, S> {
+    resolver: Arc<R>,
+    subscribe: fn(&R, &Name) -> S,
+    name: Name,
+    backoff: Backoff,
+    attempt: u32,
+    stream: Option<S>,
+    timer: Option<Delay>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempts: Option<u32>,
+}
+
+impl Backoff {
+    /// Returns the delay before the next attempt or `None` if there should
+    /// be no more attempts
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.attempts.map(|max| attempt + 1 >= max).unwrap_or(false) {
+            return None;
+        }
+        let mut delay = self.initial;
+        for _ in 0..attempt {
+            delay = min(delay * 2, self.max);
+            if delay == self.max {
+                break;
+            }
+        }
+        let delay = min(delay, self.max);
+        let millis = delay.as_secs() * 1000 +
+            u64::from(delay.subsec_nanos()) / 1_000_000;
+        Some(Duration::from_millis(
+            thread_rng().gen_range(millis / 2, millis + 1)))
+    }
+}
+
+impl<R> Retry<R> {
+    /// Create a resolver that retries temporary errors with default settings
+    pub fn new(resolver: R) -> Retry<R> {
+        Retry {
+            resolver: Arc::new(resolver),
+            backoff: Backoff {
+                initial: Duration::from_millis(100),
+                max: Duration::new(10, 0),
+                attempts: Some(5),
+            },
+        }
+    }
+    /// Set delay before the first retry
+    pub fn initial_delay(&mut self, delay: Duration) -> &mut Self {
+        self.backoff.initial = delay;
+        self
+    }
+    /// Set the maximum delay between two attempts
+    pub fn max_delay(&mut self, delay: Duration) -> &mut Self {
+        self.backoff.max = delay;
+        self
+    }
+    /// Set the maximum number of attempts (including the first one)
+    ///
+    /// `None` means retry forever. For subscriptions this is the number
+    /// of subsequent failures without a value yielded in between.
+    pub fn max_attempts(&mut self, attempts: Option<u32>) -> &mut Self {
+        self.backoff.attempts = attempts;
+        self
+    }
+}
+
+impl<R: Resolve> Resolve for Retry<R> {
+    type Future = RetryFuture<R, R::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        RetryFuture::new(&self.resolver, R::resolve, name, self.backoff)
+    }
+}
+
+impl<R: HostResolve> HostResolve for Retry<R> {
+    type HostFuture = RetryFuture<R, R::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        RetryFuture::new(&self.resolver, R::resolve_host, name, self.backoff)
+    }
+}
+
+impl<R: Subscribe> Subscribe for Retry<R> {
+    type Stream = RetryStream<R, R::Stream>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        RetryStream::new(&self.resolver, R::subscribe, name, self.backoff)
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for Retry<R> {
+    type HostStream = RetryStream<R, R::HostStream>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        RetryStream::new(&self.resolver, R::subscribe_host,
+                         name, self.backoff)
+    }
+}
+
+impl<R, F: Future<Error=Error>> RetryFuture<R, F> {
+    fn new(resolver: &Arc<R>, resolve: fn(&R, &Name) -> F,
+           name: &Name, backoff: Backoff)
+        -> RetryFuture<R, F>
+    {
+        RetryFuture {
+            future: Some(resolve(resolver, name)),
+            resolver: resolver.clone(),
+            resolve,
+            name: name.clone(),
+            backoff,
+            attempt: 0,
+            timer: None,
+        }
+    }
+}
+
+impl<R, F: Future<Error=Error>> Future for RetryFuture<R, F> {
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, Error> {
+        loop {
+            if let Some(mut timer) = self.timer.take() {
+                match timer.poll() {
+                    Ok(Async::Ready(())) => {
+                        self.future = Some(
+                            (self.resolve)(&self.resolver, &self.name));
+                    }
+                    Ok(Async::NotReady) => {
+                        self.timer = Some(timer);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => return Err(Error::TemporaryError(Box::new(e))),
+                }
+            }
+            let result = self.future.as_mut().expect("future polled twice")
+                .poll();
+            match result {
+                Err(Error::TemporaryError(_)) | Err(Error::Timeout) => {
+                    match self.backoff.delay(self.attempt) {
+                        Some(delay) => {
+                            self.attempt += 1;
+                            self.future = None;
+                            self.timer = Some(
+                                Delay::new(Instant::now() + delay));
+                        }
+                        None => return result,
+                    }
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+impl<R, S> RetryStream<R, S>
+    where S: Stream,
+          S::Error: Into<Error>,
+{
+    fn new(resolver: &Arc<R>, subscribe: fn(&R, &Name) -> S,
+           name: &Name, backoff: Backoff)
+        -> RetryStream<R, S>
+    {
+        RetryStream {
+            stream: Some(subscribe(resolver, name)),
+            resolver: resolver.clone(),
+            subscribe,
+            name: name.clone(),
+            backoff,
+            attempt: 0,
+            timer: None,
+        }
+    }
+}
+
+impl<R, S> Stream for RetryStream<R, S>
+    where S: Stream,
+          S::Error: Into<Error>,
+{
+    type Item = S::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, Error> {
+        loop {
+            if let Some(mut timer) = self.timer.take() {
+                match timer.poll() {
+                    Ok(Async::Ready(())) => {
+                        self.stream = Some(
+                            (self.subscribe)(&self.resolver, &self.name));
+                    }
+                    Ok(Async::NotReady) => {
+                        self.timer = Some(timer);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => return Err(Error::TemporaryError(Box::new(e))),
+                }
+            }
+            let result = self.stream.as_mut()
+                .expect("stream polled after error")
+                .poll().map_err(|e| e.into());
+            match result {
+                Ok(Async::Ready(Some(_))) => {
+                    self.attempt = 0;
+                    return result;
+                }
+                Err(Error::TemporaryError(_)) | Err(Error::Timeout) => {
+                    match self.backoff.delay(self.attempt) {
+                        Some(delay) => {
+                            self.attempt += 1;
+                            self.stream = None;
+                            self.timer = Some(
+                                Delay::new(Instant::now() + delay));
+                        }
+                        None => return result,
+                    }
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use super::Backoff;
+
+    #[test]
+    fn backoff() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(1000),
+            attempts: Some(6),
+        };
+        let ms = |n| Duration::from_millis(n);
+        for _ in 0..100 {
+            let d = backoff.delay(0).unwrap();
+            assert!(d >= ms(50) && d <= ms(100), "{:?}", d);
+            let d = backoff.delay(1).unwrap();
+            assert!(d >= ms(100) && d <= ms(200), "{:?}", d);
+            let d = backoff.delay(3).unwrap();
+            assert!(d >= ms(400) && d <= ms(800), "{:?}", d);
+            let d = backoff.delay(4).unwrap();
+            assert!(d >= ms(500) && d <= ms(1000), "{:?}", d);
+            assert!(backoff.delay(5).is_none());
+        }
+    }
+
+    #[test]
+    fn unlimited() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(1000),
+            attempts: None,
+        };
+        let d = backoff.delay(100).unwrap();
+        assert!(d >= Duration::from_millis(500), "{:?}", d);
+        assert!(d <= Duration::from_millis(1000), "{:?}", d);
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Stream};
+use tokio_timer::Delay;
+use {Name, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+
+
+/// A resolver that limits the time of name resolution
+///
+/// If the underlying future doesn't resolve in time, `Error::Timeout`
+/// is returned.
+///
+/// For subscriptions the timeout applies to the first value only: if the
+/// stream doesn't yield anything in time it fails with `Error::Timeout`,
+/// after the first value the stream is passed through as is (there is no
+/// way to know when the next update is expected).
+#[derive(Debug)]
+pub struct Timeout<R> {
+    resolver: R,
+    timeout: Duration,
+}
+
+/// A future returned by `Timeout` resolver
+#[derive(Debug)]
+pub struct TimeoutFuture<F> {
+    future: F,
+    timer: Delay,
+}
+
+/// A stream returned by `Timeout` subscriber
+#[derive(Debug)]
+pub struct TimeoutStream<S> {
+    stream: S,
+    timer: Option<Delay>,
+}
+
+impl<R> Timeout<R> {
+    /// Create a resolver that fails if name is not resolved in `timeout`
+    pub fn new(resolver: R, timeout: Duration) -> Timeout<R> {
+        Timeout { resolver, timeout }
+    }
+    fn timer(&self) -> Delay {
+        Delay::new(Instant::now() + self.timeout)
+    }
+}
+
+impl<R: Resolve> Resolve for Timeout<R> {
+    type Future = TimeoutFuture<R::Future>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        TimeoutFuture {
+            future: self.resolver.resolve(name),
+            timer: self.timer(),
+        }
+    }
+}
+
+impl<R: HostResolve> HostResolve for Timeout<R> {
+    type HostFuture = TimeoutFuture<R::HostFuture>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        TimeoutFuture {
+            future: self.resolver.resolve_host(name),
+            timer: self.timer(),
+        }
+    }
+}
+
+impl<R: Subscribe> Subscribe for Timeout<R> {
+    type Stream = TimeoutStream<R::Stream>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        TimeoutStream {
+            stream: self.resolver.subscribe(name),
+            timer: Some(self.timer()),
+        }
+    }
+}
+
+impl<R: HostSubscribe> HostSubscribe for Timeout<R> {
+    type HostStream = TimeoutStream<R::HostStream>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        TimeoutStream {
+            stream: self.resolver.subscribe_host(name),
+            timer: Some(self.timer()),
+        }
+    }
+}
+
+impl<F: Future<Error=Error>> Future for TimeoutFuture<F> {
+    type Item = F::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<F::Item>, Error> {
+        match self.future.poll()? {
+            Async::Ready(value) => return Ok(Async::Ready(value)),
+            Async::NotReady => {}
+        }
+        match self.timer.poll() {
+            Ok(Async::Ready(())) => Err(Error::Timeout),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => Err(Error::TemporaryError(Box::new(e))),
+        }
+    }
+}
+
+impl<S> Stream for TimeoutStream<S>
+    where S: Stream,
+          S::Error: Into<Error>,
+{
+    type Item = S::Item;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<S::Item>>, Error> {
+        match self.stream.poll().map_err(|e| e.into())? {
+            Async::NotReady => {}
+            result => {
+                self.timer = None;
+                return Ok(result);
+            }
+        }
+        match self.timer.as_mut().map(|t| t.poll()) {
+            Some(Ok(Async::Ready(()))) => Err(Error::Timeout),
+            Some(Ok(Async::NotReady)) | None => Ok(Async::NotReady),
+            Some(Err(e)) => Err(Error::TemporaryError(Box::new(e))),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Connecting to an `Address` using "Happy Eyeballs" algorithm
+//!
+//! This module is only available with the `connect` feature enabled.
+//!
+use std::cmp::max;
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future};
+use tokio_tcp::{TcpStream, ConnectFuture};
+use tokio_timer::Delay;
+use rfc6724::DestinationOrder;
+use {Address, IpList};
+
+
+/// Minimum delay between connection attempts (RFC 8305, section 5)
+const MIN_ATTEMPT_DELAY: Duration = Duration::from_millis(100);
+
+/// A connector implementing "Happy Eyeballs" (RFC 8305) algorithm
+///
+/// Addresses of the highest priority are tried first, lower priority
+/// sets are only tried when all connection attempts to the higher
+/// priority set have failed.
+///
+/// Within a single priority, addresses are sorted using
+/// `rfc6724::DestinationOrder`, then IPv6 and IPv4 addresses are
+/// interleaved (starting with the family of the first address after
+/// sorting). A new connection attempt is started every `attempt_delay`
+/// (250 milliseconds by default) or immediately when the previous attempt
+/// fails, without cancelling attempts which are in progress. The first
+/// established connection wins and all other attempts are dropped.
+///
+/// Weights are not taken into account.
+#[derive(Debug, Clone)]
+pub struct HappyEyeballs {
+    attempt_delay: Duration,
+    order: DestinationOrder,
+}
+
+/// A future returned by `HappyEyeballs::connect`
+#[derive(Debug)]
+pub struct Connect {
+    levels: VecDeque<Vec<SocketAddr>>,
+    queue: VecDeque<SocketAddr>,
+    attempts: Vec<(SocketAddr, ConnectFuture)>,
+    timer: Option<Delay>,
+    attempt_delay: Duration,
+    order: DestinationOrder,
+    errors: Vec<(SocketAddr, io::Error)>,
+}
+
+/// Successfully established connection
+#[derive(Debug)]
+pub struct Connected {
+    /// The connection itself
+    pub stream: TcpStream,
+    /// The address the connection is established to
+    pub addr: SocketAddr,
+    /// Errors of failed connection attempts made before the successful one
+    pub errors: Vec<(SocketAddr, io::Error)>,
+}
+
+/// Error returned when no connection attempt has succeeded
+#[derive(Debug)]
+pub struct ConnectError {
+    errors: Vec<(SocketAddr, io::Error)>,
+}
+
+impl HappyEyeballs {
+    /// Create a connector with default settings
+    pub fn new() -> HappyEyeballs {
+        HappyEyeballs {
+            attempt_delay: Duration::from_millis(250),
+            order: DestinationOrder::new(),
+        }
+    }
+    /// Set delay between starting subsequent connection attempts
+    ///
+    /// RFC 8305 recommends 250 milliseconds, delays shorter than the
+    /// minimum of 100 milliseconds are rounded up to it.
+    pub fn attempt_delay(&mut self, delay: Duration) -> &mut Self {
+        self.attempt_delay = max(delay, MIN_ATTEMPT_DELAY);
+        self
+    }
+    /// Set the order used to sort addresses of the same priority
+    ///
+    /// By default source addresses are chosen by the operating system,
+    /// see `DestinationOrder` for details.
+    pub fn destination_order(&mut self, order: DestinationOrder)
+        -> &mut Self
+    {
+        self.order = order;
+        self
+    }
+    /// Connect to the address
+    pub fn connect(&self, address: &Address) -> Connect {
+        Connect {
+            levels: address.iter()
+                .map(|set| set.addresses().collect())
+                .collect(),
+            queue: VecDeque::new(),
+            attempts: Vec::new(),
+            timer: None,
+            attempt_delay: self.attempt_delay,
+            order: self.order.clone(),
+            errors: Vec::new(),
+        }
+    }
+    /// Connect to one of the IP addresses using specified port
+    pub fn connect_ips(&self, ips: &IpList, port: u16) -> Connect {
+        self.connect(&ips.with_port(port))
+    }
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> HappyEyeballs {
+        HappyEyeballs::new()
+    }
+}
+
+/// Sorts addresses by their IPs, keeping the order of the same IPs
+fn sort(order: &DestinationOrder, mut addresses: Vec<SocketAddr>)
+    -> Vec<SocketAddr>
+{
+    let ips = order.sort(&addresses.iter().map(|a| a.ip()).collect());
+    addresses.sort_by_key(|a| ips.iter().position(|&ip| ip == a.ip()));
+    addresses
+}
+
+fn interleave(addresses: Vec<SocketAddr>) -> VecDeque<SocketAddr> {
+    let v6_first = matches!(addresses.first(), Some(a) if a.is_ipv6());
+    let (mut first, mut second): (VecDeque<_>, VecDeque<_>) =
+        addresses.into_iter().partition(|a| a.is_ipv6() == v6_first);
+    let mut result = VecDeque::with_capacity(first.len() + second.len());
+    loop {
+        match (first.pop_front(), second.pop_front()) {
+            (None, None) => break,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+    result
+}
+
+impl Connect {
+    fn next_addr(&mut self) -> Option<SocketAddr> {
+        if self.queue.is_empty() && self.attempts.is_empty() {
+            if let Some(level) = self.levels.pop_front() {
+                self.queue = interleave(sort(&self.order, level));
+            }
+        }
+        self.queue.pop_front()
+    }
+    fn start_next(&mut self) -> bool {
+        match self.next_addr() {
+            Some(addr) => {
+                self.attempts.push((addr, TcpStream::connect(&addr)));
+                self.timer = Some(
+                    Delay::new(Instant::now() + self.attempt_delay));
+                true
+            }
+            None => {
+                self.timer = None;
+                false
+            }
+        }
+    }
+}
+
+impl Future for Connect {
+    type Item = Connected;
+    type Error = ConnectError;
+    fn poll(&mut self) -> Result<Async<Connected>, ConnectError> {
+        loop {
+            let mut failed = false;
+            let mut idx = 0;
+            while idx < self.attempts.len() {
+                match self.attempts[idx].1.poll() {
+                    Ok(Async::Ready(stream)) => {
+                        let addr = self.attempts[idx].0;
+                        return Ok(Async::Ready(Connected {
+                            stream,
+                            addr,
+                            errors: mem::take(&mut self.errors),
+                        }));
+                    }
+                    Ok(Async::NotReady) => idx += 1,
+                    Err(e) => {
+                        let (addr, _) = self.attempts.remove(idx);
+                        self.errors.push((addr, e));
+                        failed = true;
+                    }
+                }
+            }
+            let timer_fired = match self.timer.as_mut().map(|t| t.poll()) {
+                Some(Ok(Async::Ready(()))) => true,
+                Some(Ok(Async::NotReady)) => false,
+                // can't stagger attempts without a timer, start them all
+                Some(Err(_)) => true,
+                None => false,
+            };
+            if failed || timer_fired || self.attempts.is_empty() {
+                if self.start_next() {
+                    continue;
+                }
+                if self.attempts.is_empty() {
+                    return Err(ConnectError {
+                        errors: mem::take(&mut self.errors),
+                    });
+                }
+            }
+            return Ok(Async::NotReady);
+        }
+    }
+}
+
+impl ConnectError {
+    /// Errors of every connection attempt made
+    ///
+    /// This list is empty if there were no addresses to connect to.
+    pub fn errors(&self) -> &[(SocketAddr, io::Error)] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.errors.is_empty() {
+            return f.write_str("no addresses to connect to");
+        }
+        write!(f, "all connection attempts failed: ")?;
+        for (i, (addr, err)) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", addr, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for ConnectError {}
+
+impl From<ConnectError> for io::Error {
+    fn from(err: ConnectError) -> io::Error {
+        let kind = match err.errors.last() {
+            Some((_, e)) => e.kind(),
+            None => io::ErrorKind::NotFound,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use rfc6724::DestinationOrder;
+    use super::{interleave, sort, HappyEyeballs};
+
+    fn addrs(x: &[&str]) -> Vec<SocketAddr> {
+        x.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn interleave_families() {
+        assert_eq!(
+            interleave(addrs(&["[::1]:1", "[::2]:1", "[::3]:1",
+                               "1.1.1.1:1", "2.2.2.2:1"]))
+                .into_iter().collect::<Vec<_>>(),
+            addrs(&["[::1]:1", "1.1.1.1:1", "[::2]:1", "2.2.2.2:1",
+                    "[::3]:1"]));
+        assert_eq!(
+            interleave(addrs(&["1.1.1.1:1", "2.2.2.2:1", "[::1]:1",
+                               "[::2]:1", "[::3]:1"]))
+                .into_iter().collect::<Vec<_>>(),
+            addrs(&["1.1.1.1:1", "[::1]:1", "2.2.2.2:1", "[::2]:1",
+                    "[::3]:1"]));
+        assert_eq!(
+            interleave(addrs(&["1.1.1.1:1", "2.2.2.2:1"]))
+                .into_iter().collect::<Vec<_>>(),
+            addrs(&["1.1.1.1:1", "2.2.2.2:1"]));
+    }
+
+    #[test]
+    fn sort_by_destination_order() {
+        let order = DestinationOrder::with_sources(vec![
+            "2001:db8:1::2".parse().unwrap(),
+            "198.51.100.117".parse().unwrap(),
+        ]);
+        assert_eq!(
+            sort(&order, addrs(&["198.51.100.121:1", "198.51.100.121:2",
+                                 "[2001:db8:1::1]:1"])),
+            addrs(&["[2001:db8:1::1]:1", "198.51.100.121:1",
+                    "198.51.100.121:2"]));
+        let order = DestinationOrder::with_sources(vec![
+            "198.51.100.117".parse().unwrap(),
+        ]);
+        assert_eq!(
+            sort(&order, addrs(&["[2001:db8:1::1]:1", "198.51.100.121:1"])),
+            addrs(&["198.51.100.121:1", "[2001:db8:1::1]:1"]));
+    }
+
+    #[test]
+    fn min_attempt_delay() {
+        let mut conn = HappyEyeballs::new();
+        conn.attempt_delay(Duration::from_millis(10));
+        assert_eq!(conn.attempt_delay, Duration::from_millis(100));
+        conn.attempt_delay(Duration::from_millis(300));
+        assert_eq!(conn.attempt_delay, Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,231 @@
+//! Helpers to turn address updates into a list of changes
+//!
+//! Connection pools usually need to know which endpoints were added or
+//! removed rather than the whole new `Address`. `DiffStream` wraps any
+//! `Stream<Item=Address>` (e.g. a subscription) and yields an initial
+//! `Event::Sync` followed by `Event::Changes` for every update.
+//!
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use futures::{Async, Stream};
+use addr::{Address, Weight};
+
+
+/// A single change of the address
+///
+/// Priority is the index of the weighted set, as in `Address::at`
+/// (zero is the highest priority).
+///
+/// Every `SocketAddr` is listed in the address at most once, as duplicates
+/// are merged when `Address` is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Endpoint is added
+    Added {
+        /// The address of the endpoint
+        addr: SocketAddr,
+        /// The priority of the endpoint
+        priority: usize,
+        /// The weight of the endpoint
+        weight: Weight,
+    },
+    /// Endpoint is removed
+    Removed {
+        /// The address of the endpoint
+        addr: SocketAddr,
+        /// The priority that endpoint had
+        priority: usize,
+        /// The weight that endpoint had
+        weight: Weight,
+    },
+    /// The weight of the endpoint has changed, priority is the same
+    WeightChanged {
+        /// The address of the endpoint
+        addr: SocketAddr,
+        /// The priority of the endpoint
+        priority: usize,
+        /// Previous weight
+        old: Weight,
+        /// New weight
+        new: Weight,
+    },
+    /// The priority of the endpoint has changed
+    ///
+    /// The weight might be changed at the same time, so the new weight
+    /// is included.
+    PriorityChanged {
+        /// The address of the endpoint
+        addr: SocketAddr,
+        /// Previous priority
+        old: usize,
+        /// New priority
+        new: usize,
+        /// New weight
+        weight: Weight,
+    },
+}
+
+/// An item of the `DiffStream`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The first address received from the stream
+    ///
+    /// Consumer should reset its state to this address.
+    Sync(Address),
+    /// A non-empty list of changes since the previous event
+    Changes(Vec<Change>),
+}
+
+/// A stream adapter that yields changes of the address
+///
+/// Updates that don't change anything are skipped.
+#[derive(Debug)]
+pub struct DiffStream<S> {
+    stream: S,
+    last: Option<Address>,
+}
+
+fn endpoints(addr: &Address) -> HashMap<SocketAddr, (usize, Weight)> {
+    let mut result = HashMap::new();
+    for (priority, set) in addr.iter().enumerate() {
+        for &(weight, sa) in set.entries() {
+            result.insert(sa, (priority, weight));
+        }
+    }
+    result
+}
+
+/// Returns the list of changes needed to turn `old` address into `new` one
+///
+/// Removed endpoints go first (in the order of the old address), then
+/// the added and changed ones (in the order of the new address).
+pub fn changes(old: &Address, new: &Address) -> Vec<Change> {
+    let old_map = endpoints(old);
+    let new_map = endpoints(new);
+    let mut result = Vec::new();
+    for (priority, set) in old.iter().enumerate() {
+        for &(weight, addr) in set.entries() {
+            if !new_map.contains_key(&addr) {
+                result.push(Change::Removed { addr, priority, weight });
+            }
+        }
+    }
+    for (priority, set) in new.iter().enumerate() {
+        for &(weight, addr) in set.entries() {
+            match old_map.get(&addr) {
+                None => {
+                    result.push(Change::Added { addr, priority, weight });
+                }
+                Some(&(old, _)) if old != priority => {
+                    result.push(Change::PriorityChanged {
+                        addr, old, new: priority, weight,
+                    });
+                }
+                Some(&(_, old)) if old != weight => {
+                    result.push(Change::WeightChanged {
+                        addr, priority, old, new: weight,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    result
+}
+
+impl<S> DiffStream<S> {
+    /// Wrap a stream of addresses
+    pub fn new(stream: S) -> DiffStream<S> {
+        DiffStream { stream, last: None }
+    }
+}
+
+impl<S: Stream<Item=Address>> Stream for DiffStream<S> {
+    type Item = Event;
+    type Error = S::Error;
+    fn poll(&mut self) -> Result<Async<Option<Event>>, S::Error> {
+        loop {
+            let addr = match self.stream.poll()? {
+                Async::Ready(Some(addr)) => addr,
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            };
+            let changes = match self.last {
+                Some(ref old) => changes(old, &addr),
+                None => {
+                    self.last = Some(addr.clone());
+                    return Ok(Async::Ready(Some(Event::Sync(addr))));
+                }
+            };
+            self.last = Some(addr);
+            if !changes.is_empty() {
+                return Ok(Async::Ready(Some(Event::Changes(changes))));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Future, Stream};
+    use futures::stream::iter_ok;
+    use addr::{Address, Builder};
+    use super::{Change, Event, DiffStream, changes};
+
+    fn addr(sets: &[&[(u64, &str)]]) -> Address {
+        let mut builder = Builder::new();
+        for set in sets {
+            builder.add_addresses(&set.iter()
+                .map(|&(w, a)| (w, a.parse().unwrap()))
+                .collect::<Vec<_>>());
+        }
+        builder.into_address()
+    }
+
+    #[test]
+    fn no_changes() {
+        let a = addr(&[&[(1, "127.0.0.1:80"), (2, "127.0.0.2:80")]]);
+        let b = addr(&[&[(2, "127.0.0.2:80"), (1, "127.0.0.1:80")]]);
+        assert_eq!(changes(&a, &b), vec![]);
+    }
+
+    #[test]
+    fn all_changes() {
+        let a = addr(&[
+            &[(1, "127.0.0.1:80"), (2, "127.0.0.2:80")],
+            &[(1, "127.0.0.3:80"), (1, "127.0.0.4:80")],
+        ]);
+        let b = addr(&[
+            &[(1, "127.0.0.1:80"), (5, "127.0.0.2:80"), (7, "127.0.0.3:80")],
+            &[(1, "127.0.0.5:80"), (1, "127.0.0.1:80")],
+        ]);
+        let sa = |x: &str| x.parse().unwrap();
+        assert_eq!(changes(&a, &b), vec![
+            Change::Removed { addr: sa("127.0.0.4:80"),
+                              priority: 1, weight: 1 },
+            Change::WeightChanged { addr: sa("127.0.0.2:80"),
+                                    priority: 0, old: 2, new: 5 },
+            Change::PriorityChanged { addr: sa("127.0.0.3:80"),
+                                      old: 1, new: 0, weight: 7 },
+            Change::Added { addr: sa("127.0.0.5:80"),
+                            priority: 1, weight: 1 },
+        ]);
+    }
+
+    #[test]
+    fn stream() {
+        let a = addr(&[&[(1, "127.0.0.1:80")]]);
+        let b = addr(&[&[(1, "127.0.0.1:80"), (1, "127.0.0.2:80")]]);
+        let events = DiffStream::new(iter_ok::<_, ()>(vec![
+            a.clone(), a.clone(), b.clone(), b.clone(),
+        ])).collect().wait().unwrap();
+        assert_eq!(events, vec![
+            Event::Sync(a),
+            Event::Changes(vec![
+                Change::Added { addr: "127.0.0.2:80".parse().unwrap(),
+                                priority: 0, weight: 1 },
+            ]),
+        ]);
+    }
+}
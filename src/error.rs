@@ -1,6 +1,8 @@
+use std::fmt;
 use std::error::{Error as StdError};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
+use futures::future::SharedError;
 use void::{unreachable, Void};
 
 
@@ -31,6 +33,16 @@ quick_error! {
             display("temporary name resolution error: {}", err)
             cause(&**err)
         }
+        /// Name resolution took too long
+        ///
+        /// This is returned by the `Timeout` combinator. Similarly to
+        /// `TemporaryError` it's safe to retry name resolution, but this
+        /// error means that name server didn't reply in time rather than
+        /// returned an error.
+        Timeout {
+            description("name resolution timed out")
+            display("name resolution timed out")
+        }
         /// We have sucessfully done name resolution but there is no such name
         NameNotFound {
             description("name not found")
@@ -53,6 +65,8 @@ impl Error {
                 IoError::new(IoErrorKind::InvalidInput, self),
             Error::TemporaryError(_) =>
                 IoError::new(IoErrorKind::Other, self),
+            Error::Timeout =>
+                IoError::new(IoErrorKind::TimedOut, self),
             Error::NameNotFound =>
                 IoError::new(IoErrorKind::NotFound, self),
             Error::NoDefaultPort =>
@@ -61,6 +75,47 @@ impl Error {
     }
 }
 
+/// The original error of the temporary error shared between futures
+#[derive(Debug)]
+struct SharedCause(SharedError<Error>);
+
+impl fmt::Display for SharedCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Error::TemporaryError(ref err) => err.fmt(f),
+            ref err => err.fmt(f),
+        }
+    }
+}
+
+impl StdError for SharedCause {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self.0 {
+            Error::TemporaryError(ref err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Converts an error of the `Shared` future into an owned error
+///
+/// This makes a copy of the error for each consumer of the shared future,
+/// the original error of `TemporaryError` is shared between the copies.
+impl From<SharedError<Error>> for Error {
+    fn from(err: SharedError<Error>) -> Error {
+        match *err {
+            Error::InvalidName(ref name, description) => {
+                return Error::InvalidName(name.clone(), description)
+            }
+            Error::TemporaryError(_) => {}
+            Error::Timeout => return Error::Timeout,
+            Error::NameNotFound => return Error::NameNotFound,
+            Error::NoDefaultPort => return Error::NoDefaultPort,
+        }
+        Error::TemporaryError(Box::new(SharedCause(err)))
+    }
+}
+
 impl From<Void> for Error {
     fn from(v: Void) -> Error {
         unreachable(v);
@@ -81,4 +136,20 @@ fn wrap_into_io() {
         IoErrorKind::Other);
     assert_eq!(Error::NameNotFound.into_io().kind(),
         IoErrorKind::NotFound);
+    assert_eq!(Error::Timeout.into_io().kind(),
+        IoErrorKind::TimedOut);
+}
+
+#[test]
+fn from_shared() {
+    use futures::Future;
+    use futures::future::err;
+
+    let shared = err::<(), _>(Error::TemporaryError(Box::new(
+        IoError::new(IoErrorKind::ConnectionRefused, "oh no!"))))
+        .shared();
+    let e1: Error = shared.clone().wait().unwrap_err().into();
+    let e2: Error = shared.wait().unwrap_err().into();
+    assert_eq!(e1.to_string(), "temporary name resolution error: oh no!");
+    assert_eq!(e2.to_string(), "temporary name resolution error: oh no!");
 }
@@ -0,0 +1,290 @@
+//! A resolver that reads names from hosts file (i.e. `/etc/hosts`)
+//!
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::{Async, Future, Stream};
+use futures::future::{FutureResult, ok, err};
+use tokio_timer::Delay;
+use {Name, IpList, Error};
+use {HostResolve, HostSubscribe};
+
+
+type Table = Arc<HashMap<String, IpList>>;
+
+/// Modification time is stored with this precision on most filesystems
+/// (FAT has the coarsest one), so two writes within this time after the
+/// file was read may have the same mtime
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// A resolver that looks up host names in a hosts file
+///
+/// The file is in the usual `/etc/hosts` format: an IP address (either
+/// IPv4 or IPv6) followed by a canonical name and any number of aliases,
+/// everything after `#` is a comment. If a name is listed on multiple
+/// lines, addresses from all lines are returned.
+///
+/// File is reread on resolution if its modification time or size has
+/// changed. Also, while the file was read shortly after its modification
+/// time (so it might have been changed again without updating mtime) it's
+/// reread every time and its contents are compared. Subscriptions check
+/// the file periodically (every second by default) and yield a new list
+/// of addresses whenever it is changed.
+///
+/// Names that are not in the file (or if there is no file at all) are
+/// resolved to `NameNotFound`, so this resolver is expected to be put
+/// in front of a DNS resolver using `Fallback`.
+///
+/// Reading the file is blocking, which is fine for a small local file.
+#[derive(Debug, Clone)]
+pub struct HostsFileResolver {
+    file: Arc<HostsFile>,
+    interval: Duration,
+}
+
+/// A stream returned from `HostsFileResolver::subscribe_host`
+#[derive(Debug)]
+pub struct HostsFileStream {
+    file: Arc<HostsFile>,
+    name: Name,
+    interval: Duration,
+    last_value: Option<IpList>,
+    timer: Option<Delay>,
+}
+
+#[derive(Debug)]
+struct HostsFile {
+    path: PathBuf,
+    cache: Mutex<Cache>,
+}
+
+#[derive(Debug)]
+struct Cache {
+    stamp: Option<(SystemTime, u64)>,
+    read_at: Option<SystemTime>,
+    hash: u64,
+    table: Table,
+}
+
+impl HostsFileResolver {
+    /// Create a resolver that reads system hosts file (`/etc/hosts`)
+    pub fn new() -> HostsFileResolver {
+        HostsFileResolver::with_path("/etc/hosts")
+    }
+    /// Create a resolver that reads specified file
+    pub fn with_path<P: AsRef<Path>>(path: P) -> HostsFileResolver {
+        HostsFileResolver {
+            file: Arc::new(HostsFile {
+                path: path.as_ref().to_path_buf(),
+                cache: Mutex::new(Cache {
+                    stamp: None,
+                    read_at: None,
+                    hash: 0,
+                    table: Arc::new(HashMap::new()),
+                }),
+            }),
+            interval: Duration::new(1, 0),
+        }
+    }
+    /// Set the interval of checking file for changes in subscriptions
+    pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Default for HostsFileResolver {
+    fn default() -> HostsFileResolver {
+        HostsFileResolver::new()
+    }
+}
+
+impl HostsFile {
+    fn table(&self) -> Result<Table, io::Error> {
+        let mut cache = self.cache.lock().expect("cache is not poisoned");
+        let stamp = match self.path.metadata() {
+            Ok(meta) => Some((meta.modified()?, meta.len())),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let unsure = match (stamp, cache.read_at) {
+            (Some((mtime, _)), Some(read_at)) => {
+                read_at < mtime + MTIME_GRANULARITY
+            }
+            _ => false,
+        };
+        if stamp != cache.stamp || unsure {
+            match stamp {
+                Some(_) => {
+                    let read_at = SystemTime::now();
+                    let mut text = String::new();
+                    File::open(&self.path)?.read_to_string(&mut text)?;
+                    let mut hasher = DefaultHasher::new();
+                    text.hash(&mut hasher);
+                    let hash = hasher.finish();
+                    if stamp != cache.stamp || hash != cache.hash {
+                        cache.table = Arc::new(parse(&text));
+                    }
+                    cache.read_at = Some(read_at);
+                    cache.hash = hash;
+                }
+                None => {
+                    cache.table = Arc::new(HashMap::new());
+                    cache.read_at = None;
+                }
+            }
+            cache.stamp = stamp;
+        }
+        Ok(cache.table.clone())
+    }
+    fn lookup(&self, name: &Name) -> Result<IpList, Error> {
+        let table = self.table()
+            .map_err(|e| Error::TemporaryError(Box::new(e)))?;
+        let name: &str = name.as_ref();
+        let name = name.strip_suffix('.').unwrap_or(name);
+        table.get(name).cloned().ok_or(Error::NameNotFound)
+    }
+}
+
+fn parse(text: &str) -> HashMap<String, IpList> {
+    let mut table = HashMap::<String, Vec<IpAddr>>::new();
+    for line in text.lines() {
+        let line = match line.find('#') {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+        let mut words = line.split_whitespace();
+        let ip = match words.next().and_then(|w| w.parse::<IpAddr>().ok()) {
+            Some(ip) => ip,
+            // invalid and zoned addresses (`fe80::1%lo0`) are skipped
+            None => continue,
+        };
+        for name in words {
+            let name = name.to_lowercase();
+            let name = name.strip_suffix('.').unwrap_or(&name);
+            if name.parse::<Name>().is_err() {
+                continue;
+            }
+            let ips = table.entry(name.to_string()).or_default();
+            if !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+    }
+    table.into_iter().map(|(name, ips)| (name, IpList::from(ips))).collect()
+}
+
+impl HostResolve for HostsFileResolver {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        match self.file.lookup(name) {
+            Ok(ips) => ok(ips),
+            Err(e) => err(e),
+        }
+    }
+}
+
+impl HostSubscribe for HostsFileResolver {
+    type HostStream = HostsFileStream;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        HostsFileStream {
+            file: self.file.clone(),
+            name: name.clone(),
+            interval: self.interval,
+            last_value: None,
+            timer: None,
+        }
+    }
+}
+
+impl Stream for HostsFileStream {
+    type Item = IpList;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<IpList>>, Error> {
+        loop {
+            if let Some(ref mut timer) = self.timer {
+                match timer.poll() {
+                    Ok(Async::Ready(())) => {}
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => return Err(Error::TemporaryError(Box::new(e))),
+                }
+            }
+            self.timer = Some(Delay::new(Instant::now() + self.interval));
+            let ips = self.file.lookup(&self.name)?;
+            if self.last_value.as_ref() != Some(&ips) {
+                self.last_value = Some(ips.clone());
+                return Ok(Async::Ready(Some(ips)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{File, remove_file};
+    use std::io::Write;
+    use std::sync::Mutex;
+    use super::{parse, HostsFile, Cache};
+    use IpList;
+
+    #[test]
+    fn parse_hosts() {
+        let table = parse("\
+            # comment\n\
+            127.0.0.1 localhost\n\
+            ::1       localhost ip6-localhost  # trailing comment\n\
+            \n\
+            10.0.0.1\tExample.org. www.example.org\n\
+            10.0.0.2 www.example.org\n\
+            10.0.0.1 www.example.org\n\
+            fe80::1%lo0 link-local\n\
+            garbage line\n\
+            10.0.0.3 bad!name good\n\
+        ");
+        let ips = |x: &[&str]| IpList::parse_list(x).unwrap();
+        assert_eq!(table["localhost"], ips(&["127.0.0.1", "::1"]));
+        assert_eq!(table["ip6-localhost"], ips(&["::1"]));
+        assert_eq!(table["example.org"], ips(&["10.0.0.1"]));
+        assert_eq!(table["www.example.org"], ips(&["10.0.0.1", "10.0.0.2"]));
+        assert_eq!(table["good"], ips(&["10.0.0.3"]));
+        assert_eq!(table.len(), 5);
+    }
+
+    #[test]
+    fn same_stamp_rewrite() {
+        let path = env::temp_dir().join(format!(
+            "abstract-ns-{}-same-stamp", ::std::process::id()));
+        let write = |data: &str| {
+            File::create(&path).unwrap().write_all(data.as_bytes()).unwrap();
+        };
+        write("127.0.0.1 aaa\n");
+        let file = HostsFile {
+            path: path.clone(),
+            cache: Mutex::new(Cache {
+                stamp: None,
+                read_at: None,
+                hash: 0,
+                table: Default::default(),
+            }),
+        };
+        assert_eq!(file.lookup(&"aaa".parse().unwrap()).unwrap(),
+                   IpList::parse_list(&["127.0.0.1"]).unwrap());
+        write("127.0.0.2 bbb\n");
+        // pretend that mtime hasn't changed (coarse granularity)
+        let meta = path.metadata().unwrap();
+        file.cache.lock().unwrap().stamp =
+            Some((meta.modified().unwrap(), meta.len()));
+        assert_eq!(file.lookup(&"bbb".parse().unwrap()).unwrap(),
+                   IpList::parse_list(&["127.0.0.2"]).unwrap());
+        remove_file(&path).unwrap();
+    }
+}
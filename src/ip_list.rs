@@ -7,6 +7,8 @@ use std::sync::Arc;
 use std::iter::FromIterator;
 
 use rand::{thread_rng, Rng};
+#[cfg(feature="serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use addr::Address;
 
 /// IpList is a wrapper type around `Vec<IpAddr>` which serves the same
@@ -26,6 +28,10 @@ impl IpList {
     ///
     /// This function selects a random address from the list of addresses or
     /// `None` if list is empty.
+    ///
+    /// Both IPv4 and IPv6 addresses may be picked, regardless of whether
+    /// the host can reach them. Use `rfc6724::DestinationOrder` to find out
+    /// which addresses should be tried first.
     pub fn pick_one(&self) -> Option<IpAddr> {
         if self.0.len() == 0 {
             return None
@@ -73,6 +79,25 @@ impl<'a> IntoIterator for &'a IpList {
     }
 }
 
+/// Serializes as a list of strings (in human-readable formats)
+#[cfg(feature="serde")]
+impl Serialize for IpList {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature="serde")]
+impl<'de> Deserialize<'de> for IpList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D)
+        -> Result<IpList, D::Error>
+    {
+        Vec::deserialize(deserializer).map(IpList::from)
+    }
+}
+
 impl From<Vec<IpAddr>> for IpList {
     fn from(vec: Vec<IpAddr>) -> IpList {
         IpList(Arc::new(vec))
@@ -58,7 +58,10 @@
 //!
 //! 1. Clients: when you need to connect once, accept
 //!    `T: Future<Item=SocketAddr>`, there are adapters that pick a random
-//!    host from `Future<Item=Address>` returned by `PollResolver::resolve`
+//!    host from `Future<Item=Address>` returned by `PollResolver::resolve`.
+//!    Or use [`HappyEyeballs`](connect/struct.HappyEyeballs.html)
+//!    connector (`connect` feature) to establish connection to an
+//!    `Address` directly
 //! 2. Clients: when writing a connection pool, accept
 //!    `T: Stream<Item=Address>`, there are adapters to make that stream
 //!    by resolving a single name (into potentially multiple IP addresses),
@@ -82,31 +85,72 @@
 //! Applications should use `ns-router` crate that supports multiple resolvers,
 //! and configuring them on-the-fly.
 //!
+//! Small applications which don't need to reconfigure resolvers might use
+//! [`SuffixRouter`](router/struct.SuffixRouter.html) which selects resolver
+//! by the suffix of the name.
+//!
+//! Names which are known in advance (or overridden for tests) can be put
+//! into [`StaticResolver`](static_resolver/struct.StaticResolver.html).
+//! Use [`HostsFileResolver`](hosts_file/struct.HostsFileResolver.html)
+//! in front of DNS resolver to take `/etc/hosts` into account.
+//!
+//! Targets from configuration files, like `example.org:8080` or `[::1]:80`,
+//! can be parsed into [`HostPort`](name/struct.HostPort.html) and resolved
+//! by any resolver.
+//!
 //! # Writing Connection Pools
 //!
 //! As said in [Writing Protocols](#writing-protocols) section a single
 //! connection pool should use `T: Stream<Item=Address>` for as a name
 //! source, this allows good flexibility (also see [tk-pool])
 //!
+//! Use [`DiffStream`](diff/struct.DiffStream.html) to get the list of
+//! added and removed endpoints instead of the whole `Address` on every
+//! update.
+//!
 //! But in case you need kinda connection pool to a lot of different names
 //! and services, this is the good case for accepting `Resolver` trait itself.
 //! (Still, most of the time actual application should supply
 //!  `ns_router::Router`)
 //!
+//! # Testing
+//!
+//! Enable the `testing` feature to get
+//! [`MockResolver`](testing/struct.MockResolver.html) which returns
+//! programmed responses and plays scripted updates to subscribers.
+//!
+//! # Serialization
+//!
+//! Enable the `serde` feature to serialize and deserialize `Address`,
+//! `IpList` and `Name`. Names are validated when deserialized.
+//!
 #![deny(missing_docs)]
 #![deny(missing_debug_implementations)]
 
 extern crate futures;
 extern crate rand;
 extern crate void;
+extern crate tokio_timer;
+#[cfg(feature="connect")] extern crate tokio_tcp;
+#[cfg(feature="serde")] extern crate serde;
 #[macro_use] extern crate quick_error;
 
 mod error;
 mod resolver;
 pub mod addr;
+pub mod balance;
 pub mod name;
+pub mod picker;
 pub mod ip_list;
 pub mod combinators;
+#[cfg(feature="connect")] pub mod connect;
+pub mod diff;
+pub mod hosts_file;
+pub mod rfc6724;
+pub mod router;
+pub mod static_resolver;
+mod watchers;
+#[cfg(feature="testing")] pub mod testing;
 
 pub use addr::Address;
 pub use ip_list::IpList;
@@ -119,3 +163,9 @@ impl AssertTraits for Address {}
 impl AssertTraits for IpList {}
 impl AssertTraits for Name {}
 impl AssertTraits for Error {}
+impl AssertTraits for combinators::BoxResolver {}
+impl AssertTraits for combinators::BoxHostResolver {}
+impl AssertTraits for combinators::BoxSubscriber {}
+impl AssertTraits for combinators::BoxHostSubscriber {}
+impl AssertTraits for static_resolver::StaticResolver {}
+impl AssertTraits for hosts_file::HostsFileResolver {}
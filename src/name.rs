@@ -3,10 +3,21 @@
 use std::fmt;
 #[allow(unused_imports, deprecated)]
 use std::ascii::AsciiExt;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
 use std::num::ParseIntError;
 use std::sync::Arc;
 
+use futures::{Async, Future};
+use futures::future::Empty;
+use {Address, IpList, Resolve, HostResolve};
+use {Error as ResolveError};
+
+#[cfg(feature="serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature="serde")]
+use serde::de::Error as DeError;
+
 quick_error! {
     /// Error parsing Name from string
     #[derive(Debug)]
@@ -27,9 +38,54 @@ quick_error! {
              display("default port number is invalid: {}", err)
              from()
         }
+        InvalidBrackets {
+            description("only IPv6 address can be enclosed in brackets \
+                and port can only follow the closing bracket")
+        }
     }
 }
 
+/// Host part of the `HostPort`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Host {
+    /// Host name that needs to be resolved
+    Name(Name),
+    /// IP address literal
+    Ip(IpAddr),
+}
+
+/// A host name or an IP address with an optional port
+///
+/// This is what is usually written in configuration files to point to
+/// a service. The following forms are accepted:
+///
+/// * `example.org` -- a name, resolved using `Resolve` (i.e. it's a service
+///   name which contains port numbers, like `SRV` record)
+/// * `example.org:8080` -- a host name resolved using `HostResolve`, and
+///   the port is attached to each IP address
+/// * `127.0.0.1:80`, `[::1]:80` -- IP address and port, no resolution
+///   needed
+/// * `127.0.0.1`, `::1`, `[::1]` -- IP address without port, can't be
+///   resolved into an `Address` (`Error::NoDefaultPort` is returned)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostPort {
+    host: Host,
+    port: Option<u16>,
+}
+
+/// A future returned by `HostPort::resolve` and `HostPort::resolve_host`
+#[derive(Debug)]
+pub struct HostPortFuture<F, H> {
+    state: State<F, H>,
+}
+
+#[derive(Debug)]
+enum State<F, H> {
+    Ready(Option<Result<Address, ResolveError>>),
+    Service(F),
+    Host(H, u16),
+}
+
 /// A name is a barely ``Arc<String>`` but also checks that name is valid
 ///
 /// Note: this is designed to be static, because it's often used inside
@@ -69,6 +125,28 @@ impl FromStr for Name {
     }
 }
 
+#[cfg(feature="serde")]
+impl Serialize for Name {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Deserializes a string, it's validated the same way as in `from_str`
+#[cfg(feature="serde")]
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D)
+        -> Result<Name, D::Error>
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(|e| {
+            D::Error::custom(format!("invalid name {:?}: {}", value, e))
+        })
+    }
+}
+
 fn namecheck(mut name: &str) -> Result<(), Error> {
     // The dot at the end is allowed (means don't add search domain)
     if name.ends_with('.') {
@@ -98,11 +176,154 @@ impl fmt::Display for Name {
     }
 }
 
+impl HostPort {
+    /// Create a new value from a host and an optional port
+    pub fn new(host: Host, port: Option<u16>) -> HostPort {
+        HostPort { host, port }
+    }
+    /// Returns the host part
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+    /// Returns the port if specified
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+    /// Resolve into an address
+    ///
+    /// Name without port is resolved using `Resolve::resolve`, name with
+    /// port is resolved using `HostResolve::resolve_host`. IP address with
+    /// port is returned as is and IP address without port is an error.
+    ///
+    /// For resolvers that implement only `HostResolve` use `resolve_host`.
+    pub fn resolve<R>(&self, resolver: &R)
+        -> HostPortFuture<R::Future, R::HostFuture>
+        where R: Resolve + HostResolve,
+    {
+        let state = match (&self.host, self.port) {
+            (Host::Name(name), None) => {
+                State::Service(resolver.resolve(name))
+            }
+            (Host::Name(name), Some(port)) => {
+                State::Host(resolver.resolve_host(name), port)
+            }
+            (&Host::Ip(ip), Some(port)) => {
+                State::Ready(Some(Ok(SocketAddr::new(ip, port).into())))
+            }
+            (&Host::Ip(_), None) => {
+                State::Ready(Some(Err(ResolveError::NoDefaultPort)))
+            }
+        };
+        HostPortFuture { state }
+    }
+    /// Resolve into an address using only `HostResolve`
+    ///
+    /// Works like `resolve` except name without port can't be resolved
+    /// and `Error::NoDefaultPort` is returned for it (you may add
+    /// a default port using `HostResolve::with_default_port` and use
+    /// `resolve` instead).
+    pub fn resolve_host<R>(&self, resolver: &R)
+        -> HostPortFuture<Empty<Address, ResolveError>, R::HostFuture>
+        where R: HostResolve,
+    {
+        let state = match (&self.host, self.port) {
+            (Host::Name(name), Some(port)) => {
+                State::Host(resolver.resolve_host(name), port)
+            }
+            (&Host::Ip(ip), Some(port)) => {
+                State::Ready(Some(Ok(SocketAddr::new(ip, port).into())))
+            }
+            (_, None) => {
+                State::Ready(Some(Err(ResolveError::NoDefaultPort)))
+            }
+        };
+        HostPortFuture { state }
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, Error> {
+    port.parse().map_err(|e| ErrorEnum::InvalidPort(e).into())
+}
+
+impl FromStr for HostPort {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<HostPort, Error> {
+        if let Ok(ip) = value.parse() {
+            return Ok(HostPort { host: Host::Ip(ip), port: None });
+        }
+        if value.starts_with('[') {
+            let end = value.find(']')
+                .ok_or_else(|| Error::from(ErrorEnum::InvalidBrackets))?;
+            let ip = value[1..end].parse::<Ipv6Addr>()
+                .map_err(|_| Error::from(ErrorEnum::InvalidBrackets))?;
+            let port = match &value[end+1..] {
+                "" => None,
+                rest if rest.starts_with(':') => Some(parse_port(&rest[1..])?),
+                _ => return Err(ErrorEnum::InvalidBrackets.into()),
+            };
+            return Ok(HostPort { host: Host::Ip(ip.into()), port });
+        }
+        let (host, port) = match value.rfind(':') {
+            Some(idx) => (&value[..idx], Some(parse_port(&value[idx+1..])?)),
+            None => (value, None),
+        };
+        let host = match host.parse() {
+            Ok(ip) => Host::Ip(ip),
+            Err(_) => Host::Name(host.parse()?),
+        };
+        Ok(HostPort { host, port })
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Host::Name(ref name) => name.fmt(f),
+            Host::Ip(ref ip) => ip.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.host, self.port) {
+            (Host::Ip(IpAddr::V6(ip)), Some(port)) => {
+                write!(f, "[{}]:{}", ip, port)
+            }
+            (host, Some(port)) => write!(f, "{}:{}", host, port),
+            (host, None) => host.fmt(f),
+        }
+    }
+}
+
+impl<F, H> Future for HostPortFuture<F, H>
+    where F: Future<Item=Address, Error=ResolveError>,
+          H: Future<Item=IpList, Error=ResolveError>,
+{
+    type Item = Address;
+    type Error = ResolveError;
+    fn poll(&mut self) -> Result<Async<Address>, ResolveError> {
+        match self.state {
+            State::Ready(ref mut result) => {
+                result.take().expect("future polled after completion")
+                    .map(Async::Ready)
+            }
+            State::Service(ref mut future) => future.poll(),
+            State::Host(ref mut future, port) => {
+                match future.poll()? {
+                    Async::Ready(ips) => Ok(Async::Ready(ips.with_port(port))),
+                    Async::NotReady => Ok(Async::NotReady),
+                }
+            }
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
-    use super::Name;
+    use super::{Name, Host, HostPort};
 
     fn name_str(src: &str) -> Name {
         Name::from_str(src).unwrap()
@@ -168,4 +389,38 @@ mod test {
         assert_eq!(name_err("name.org.."),
             "name can\'t start with dot and can\'t have subsequent dots");
     }
+
+    fn host_port(src: &str) -> (Host, Option<u16>) {
+        let hp = HostPort::from_str(src).unwrap();
+        assert_eq!(hp.to_string(), src);
+        (hp.host().clone(), hp.port())
+    }
+
+    #[test]
+    fn host_port_parse() {
+        let ip = |x: &str| Host::Ip(x.parse().unwrap());
+        assert_eq!(host_port("example.org"),
+                   (Host::Name(bare("example.org")), None));
+        assert_eq!(host_port("example.org:8080"),
+                   (Host::Name(bare("example.org")), Some(8080)));
+        assert_eq!(host_port("127.0.0.1"), (ip("127.0.0.1"), None));
+        assert_eq!(host_port("127.0.0.1:80"), (ip("127.0.0.1"), Some(80)));
+        assert_eq!(host_port("::1"), (ip("::1"), None));
+        assert_eq!(host_port("[::1]:80"), (ip("::1"), Some(80)));
+        assert_eq!(HostPort::from_str("[::1]").unwrap(),
+                   HostPort::new(ip("::1"), None));
+    }
+
+    #[test]
+    fn host_port_errors() {
+        let err = |x| HostPort::from_str(x).is_err();
+        assert!(err("example.org:"));
+        assert!(err("example.org:http"));
+        assert!(err("example.org:65536"));
+        assert!(err("[example.org]:80"));
+        assert!(err("[::1"));
+        assert!(err("[::1]80"));
+        assert!(err("::1:80:x"));
+        assert!(err("-example.org:80"));
+    }
 }
@@ -0,0 +1,272 @@
+//! Stateful selection of addresses with failure tracking
+//!
+use std::cmp::min;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use addr::{Address, Weight};
+use balance::{Strategy, WeightedRandom, hash_key};
+
+
+/// Picks addresses taking connection failures into account
+///
+/// Unlike `Address::pick_one` this object has a state: user reports
+/// whether connection to the address picked succeeded or failed. Failed
+/// addresses are ejected, i.e. not picked for some time. Ejection time
+/// starts with `initial_backoff` (1 second by default) and doubles with
+/// each subsequent failure up to `max_backoff` (1 minute by default).
+/// When ejection time is over, the address may be picked again, and a
+/// single success resets its failure counter.
+///
+/// Addresses are picked from the highest priority set which has at least
+/// one address that is not ejected, using the load balancing strategy
+/// (weighted random by default, see `with_strategy`). So when all
+/// addresses of the `at(0)` are ejected, addresses from `at(1)` are used,
+/// and so on.
+#[derive(Debug, Clone)]
+pub struct Picker<S=WeightedRandom> {
+    levels: Vec<Vec<Endpoint>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    strategy: S,
+}
+
+#[derive(Debug, Clone)]
+struct Endpoint {
+    addr: SocketAddr,
+    weight: Weight,
+    failures: u32,
+    ejected_until: Option<Instant>,
+}
+
+impl Endpoint {
+    fn is_available(&self, now: Instant) -> bool {
+        self.ejected_until.map(|t| t <= now).unwrap_or(true)
+    }
+}
+
+fn levels(address: &Address) -> Vec<Vec<Endpoint>> {
+    address.iter().map(|set| {
+        set.entries().iter().map(|&(weight, addr)| Endpoint {
+            addr,
+            weight,
+            failures: 0,
+            ejected_until: None,
+        }).collect()
+    }).collect()
+}
+
+impl Picker {
+    /// Create a picker for the address
+    pub fn new(address: &Address) -> Picker {
+        Picker::with_strategy(address, WeightedRandom)
+    }
+}
+
+impl<S: Strategy> Picker<S> {
+    /// Create a picker using specified load balancing strategy
+    pub fn with_strategy(address: &Address, strategy: S) -> Picker<S> {
+        Picker {
+            levels: levels(address),
+            initial_backoff: Duration::new(1, 0),
+            max_backoff: Duration::new(60, 0),
+            strategy,
+        }
+    }
+    /// Returns a reference to the load balancing strategy
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+    /// Returns a mutable reference to the load balancing strategy
+    ///
+    /// This is useful to report requests to `LeastOutstanding` strategy.
+    pub fn strategy_mut(&mut self) -> &mut S {
+        &mut self.strategy
+    }
+    /// Set time the address is ejected for after the first failure
+    pub fn initial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.initial_backoff = backoff;
+        self
+    }
+    /// Set maximum time the address is ejected for
+    pub fn max_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.max_backoff = backoff;
+        self
+    }
+    /// Replace the address keeping failure state of the addresses which
+    /// are still there
+    pub fn update(&mut self, address: &Address) {
+        let mut old = HashMap::new();
+        for endpoint in self.levels.drain(..).flatten() {
+            old.insert(endpoint.addr, endpoint);
+        }
+        self.levels = levels(address);
+        for endpoint in self.levels.iter_mut().flatten() {
+            if let Some(prev) = old.get(&endpoint.addr) {
+                endpoint.failures = prev.failures;
+                endpoint.ejected_until = prev.ejected_until;
+            }
+        }
+    }
+    /// Pick an address to connect to
+    ///
+    /// Returns `None` if address is empty or all addresses are ejected.
+    pub fn pick(&mut self) -> Option<SocketAddr> {
+        self.pick_at(Instant::now(), 0)
+    }
+    /// Pick an address for the request having the key
+    ///
+    /// The key is only used by strategies that provide affinity, such as
+    /// `Rendezvous`.
+    pub fn pick_by_key<K: Hash + ?Sized>(&mut self, key: &K)
+        -> Option<SocketAddr>
+    {
+        self.pick_at(Instant::now(), hash_key(key))
+    }
+    fn pick_at(&mut self, now: Instant, key: u64) -> Option<SocketAddr> {
+        for level in &self.levels {
+            let available = level.iter()
+                .filter(|e| e.is_available(now))
+                .map(|e| (e.weight, e.addr))
+                .collect::<Vec<_>>();
+            if !available.is_empty() {
+                return self.strategy.pick(&available, key);
+            }
+        }
+        None
+    }
+    /// Report that connection to the address succeeded
+    pub fn success(&mut self, addr: SocketAddr) {
+        for endpoint in self.endpoints_mut(addr) {
+            endpoint.failures = 0;
+            endpoint.ejected_until = None;
+        }
+    }
+    /// Report that connection to the address failed
+    ///
+    /// The address is ejected until backoff time passes.
+    pub fn failure(&mut self, addr: SocketAddr) {
+        self.failure_at(addr, Instant::now())
+    }
+    fn failure_at(&mut self, addr: SocketAddr, now: Instant) {
+        let (initial, max) = (self.initial_backoff, self.max_backoff);
+        for endpoint in self.endpoints_mut(addr) {
+            let mut backoff = initial;
+            for _ in 0..endpoint.failures {
+                backoff = min(backoff * 2, max);
+                if backoff == max {
+                    break;
+                }
+            }
+            endpoint.failures = endpoint.failures.saturating_add(1);
+            endpoint.ejected_until = Some(now + min(backoff, max));
+        }
+    }
+    /// Returns true if address is currently ejected
+    pub fn is_ejected(&self, addr: SocketAddr) -> bool {
+        let now = Instant::now();
+        self.levels.iter().flatten()
+            .any(|e| e.addr == addr && !e.is_available(now))
+    }
+    fn endpoints_mut<'x>(&'x mut self, addr: SocketAddr)
+        -> impl Iterator<Item=&'x mut Endpoint> + 'x
+    {
+        self.levels.iter_mut().flatten()
+            .filter(move |e| e.addr == addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+    use addr::Builder;
+    use balance::RoundRobin;
+    use super::Picker;
+
+    fn sa(x: &str) -> SocketAddr {
+        x.parse().unwrap()
+    }
+
+    fn picker() -> Picker {
+        let mut builder = Builder::new();
+        builder.add_addresses(&[(1, sa("127.0.0.1:80")),
+                                (1, sa("127.0.0.2:80"))]);
+        builder.add_addresses(&[(1, sa("127.0.0.3:80"))]);
+        let mut picker = Picker::new(&builder.into_address());
+        picker.initial_backoff(Duration::from_millis(100));
+        picker.max_backoff(Duration::from_millis(300));
+        picker
+    }
+
+    #[test]
+    fn failover() {
+        let mut p = picker();
+        let now = Instant::now();
+        for _ in 0..20 {
+            assert_ne!(p.pick_at(now, 0), Some(sa("127.0.0.3:80")));
+        }
+        p.failure_at(sa("127.0.0.1:80"), now);
+        for _ in 0..20 {
+            assert_eq!(p.pick_at(now, 0), Some(sa("127.0.0.2:80")));
+        }
+        p.failure_at(sa("127.0.0.2:80"), now);
+        assert_eq!(p.pick_at(now, 0), Some(sa("127.0.0.3:80")));
+        p.failure_at(sa("127.0.0.3:80"), now);
+        assert_eq!(p.pick_at(now, 0), None);
+        p.success(sa("127.0.0.2:80"));
+        assert_eq!(p.pick_at(now, 0), Some(sa("127.0.0.2:80")));
+    }
+
+    #[test]
+    fn backoff() {
+        let addr = sa("127.0.0.1:80");
+        let mut p = Picker::new(&addr.into());
+        p.initial_backoff(Duration::from_millis(100));
+        p.max_backoff(Duration::from_millis(300));
+        let ms = |n| Duration::from_millis(n);
+        let now = Instant::now();
+        p.failure_at(addr, now);
+        assert_eq!(p.pick_at(now + ms(99), 0), None);
+        assert_eq!(p.pick_at(now + ms(100), 0), Some(addr));
+        p.failure_at(addr, now + ms(100));
+        assert_eq!(p.pick_at(now + ms(299), 0), None);
+        assert_eq!(p.pick_at(now + ms(300), 0), Some(addr));
+        p.failure_at(addr, now + ms(300));
+        p.failure_at(addr, now + ms(600));
+        // capped by max_backoff
+        assert_eq!(p.pick_at(now + ms(899), 0), None);
+        assert_eq!(p.pick_at(now + ms(900), 0), Some(addr));
+    }
+
+    #[test]
+    fn update_keeps_state() {
+        let mut p = picker();
+        let now = Instant::now();
+        p.failure_at(sa("127.0.0.1:80"), now);
+        let mut builder = Builder::new();
+        builder.add_addresses(&[(1, sa("127.0.0.1:80"))]);
+        builder.add_addresses(&[(1, sa("127.0.0.4:80"))]);
+        p.update(&builder.into_address());
+        assert_eq!(p.pick_at(now, 0), Some(sa("127.0.0.4:80")));
+        assert!(p.is_ejected(sa("127.0.0.1:80")));
+    }
+
+    #[test]
+    fn strategy() {
+        let mut builder = Builder::new();
+        builder.add_addresses(&[(1, sa("127.0.0.1:80")),
+                                (1, sa("127.0.0.2:80")),
+                                (1, sa("127.0.0.3:80"))]);
+        let mut p = Picker::with_strategy(&builder.into_address(),
+                                          RoundRobin::new());
+        let now = Instant::now();
+        p.failure_at(sa("127.0.0.2:80"), now);
+        let seq = (0..4).map(|_| p.pick_at(now, 0).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(seq, vec![sa("127.0.0.1:80"), sa("127.0.0.3:80"),
+                             sa("127.0.0.1:80"), sa("127.0.0.3:80")]);
+    }
+}
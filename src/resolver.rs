@@ -1,10 +1,14 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::Future;
 use futures::stream::Stream;
 use error::Error;
 
 use combinators::{FrozenSubscriber, NullResolver, NullHostResolver};
+use combinators::{IntervalSubscriber, WithDefaultPort, SubscribeMany};
+use combinators::{BoxResolver, BoxHostResolver};
+use combinators::{BoxSubscriber, BoxHostSubscriber};
 use {Name, Address, IpList};
 
 
@@ -38,6 +42,34 @@ pub trait HostResolve {
         FrozenSubscriber { resolver: self }
     }
 
+    /// Create a subscriber that resolves a host name periodically
+    ///
+    /// The name is resolved again every `period` (with a small random
+    /// jitter), and the stream yields a value only when the `IpList`
+    /// has actually changed. Temporary errors are skipped, so the stream
+    /// continues to work when the name server is unavailable for some time.
+    ///
+    /// This is a shim for resolvers that can't subscribe to updates
+    fn interval_host_subscriber(self, period: Duration)
+        -> IntervalSubscriber<Self>
+        where Self: Sized
+    {
+        IntervalSubscriber { resolver: Arc::new(self), period }
+    }
+
+    /// Create a resolver that implements `Resolve` by adding a default
+    /// port to every resolved IP address
+    ///
+    /// The result also implements `Subscribe` if this resolver implements
+    /// `HostSubscribe`. This is useful to pass hostname-only resolvers to
+    /// the code that accepts `Resolve`, for example when there is a well
+    /// known port for the protocol like `80` for http.
+    fn with_default_port(self, port: u16) -> WithDefaultPort<Self>
+        where Self: Sized
+    {
+        WithDefaultPort { resolver: self, port }
+    }
+
     /// Create a thing that implements Resolve+HostResolve but returns
     /// `NameNotFound` on `resolve`
     ///
@@ -48,13 +80,24 @@ pub trait HostResolve {
     {
         NullResolver { resolver: self }
     }
+
+    /// Convert this resolver into a type-erased `BoxHostResolver`
+    ///
+    /// This is useful to keep resolvers of different types in a collection
+    /// or to choose resolver at runtime.
+    fn boxed_host_resolver(self) -> BoxHostResolver
+        where Self: Sized + Send + Sync + 'static,
+              Self::HostFuture: Send + 'static,
+    {
+        BoxHostResolver::new(self)
+    }
 }
 
 /// Resolves a name of the service in to a set of addresses
 ///
 /// This is commonly done using SRV records, but can also be done
 /// as a wrapper around resolver by resolving a host and adding a
-/// default value (see `HostResolve::with_default_port`.
+/// default value (see `HostResolve::with_default_port`).
 pub trait Resolve {
     /// A future returned from `resolve()`
     type Future: Future<Item=Address, Error=Error>;
@@ -82,6 +125,20 @@ pub trait Resolve {
         FrozenSubscriber { resolver: self }
     }
 
+    /// Create a subscriber that resolves a service name periodically
+    ///
+    /// The name is resolved again every `period` (with a small random
+    /// jitter), and the stream yields a value only when the `Address`
+    /// has actually changed. Temporary errors are skipped, so the stream
+    /// continues to work when the name server is unavailable for some time.
+    ///
+    /// This is a shim for resolvers that can't subscribe to updates
+    fn interval_subscriber(self, period: Duration) -> IntervalSubscriber<Self>
+        where Self: Sized
+    {
+        IntervalSubscriber { resolver: Arc::new(self), period }
+    }
+
     /// Create a thing that implements Resolve+HostResolve but returns
     /// `NameNotFound` on `resolve_host`
     ///
@@ -92,6 +149,17 @@ pub trait Resolve {
     {
         NullHostResolver { resolver: self }
     }
+
+    /// Convert this resolver into a type-erased `BoxResolver`
+    ///
+    /// This is useful to keep resolvers of different types in a collection
+    /// or to choose resolver at runtime.
+    fn boxed_resolver(self) -> BoxResolver
+        where Self: Sized + Send + Sync + 'static,
+              Self::Future: Send + 'static,
+    {
+        BoxResolver::new(self)
+    }
 }
 
 /// A resolver that allows to subscribe on the host name and receive updates
@@ -109,7 +177,8 @@ pub trait HostSubscribe {
     /// no reason to shutdown pool if there is a temporary error in name
     /// resolution (and all errors should be considered temporary as
     /// user can even fix invalid name by fixing configuration file while
-    /// connection pool is operating).
+    /// connection pool is operating). Use `combinators::LastKnownGood`
+    /// to turn any subscriber into one that never fails.
     type HostError: Into<Error>;
 
     /// A stream returned from `subscribe()`
@@ -127,6 +196,16 @@ pub trait HostSubscribe {
     /// should be returned so middleware and routers can failover to other
     /// sources and put errors to log.
     fn subscribe_host(&self, name: &Name) -> Self::HostStream;
+
+    /// Convert this subscriber into a type-erased `BoxHostSubscriber`
+    ///
+    /// Errors of the stream are converted into `abstract_ns::Error`.
+    fn boxed_host_subscriber(self) -> BoxHostSubscriber
+        where Self: Sized + Send + Sync + 'static,
+              Self::HostStream: Send + 'static,
+    {
+        BoxHostSubscriber::new(self)
+    }
 }
 
 /// A resolver that allows to subscribe on the service name
@@ -144,7 +223,8 @@ pub trait Subscribe {
     /// no reason to shutdown pool if there is a temporary error in name
     /// resolution (and all errors should be considered temporary as
     /// user can even fix invalid name by fixing configuration file while
-    /// connection pool is operating).
+    /// connection pool is operating). Use `combinators::LastKnownGood`
+    /// to turn any subscriber into one that never fails.
     type Error: Into<Error>;
 
     /// A stream returned from `subscribe()`
@@ -162,6 +242,29 @@ pub trait Subscribe {
     /// should be returned so middleware and routers can failover to other
     /// sources and put errors to log.
     fn subscribe(&self, name: &Name) -> Self::Stream;
+
+    /// Subscribe to a changing list of names and merge their addresses
+    ///
+    /// Every time a new list arrives from `names` stream, subscriptions
+    /// are updated to match the list. The returned stream yields the union
+    /// of addresses of all names whenever any of them changes.
+    /// See `SubscribeMany` for details.
+    fn subscribe_many<S>(self, names: S) -> SubscribeMany<Self, S>
+        where Self: Sized,
+              S: Stream<Item=Vec<Name>>,
+    {
+        SubscribeMany::new(self, names)
+    }
+
+    /// Convert this subscriber into a type-erased `BoxSubscriber`
+    ///
+    /// Errors of the stream are converted into `abstract_ns::Error`.
+    fn boxed_subscriber(self) -> BoxSubscriber
+        where Self: Sized + Send + Sync + 'static,
+              Self::Stream: Send + 'static,
+    {
+        BoxSubscriber::new(self)
+    }
 }
 
 impl<T: Resolve> Resolve for Arc<T> {
@@ -0,0 +1,330 @@
+//! Ordering of destination addresses according to RFC 6724
+//!
+use std::cmp::Ordering;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use ip_list::IpList;
+
+
+/// Sorts destination addresses as described in RFC 6724 (section 6)
+///
+/// The following rules are applied (in this order) to find out which
+/// address should be tried first:
+///
+/// 1. Avoid unusable destinations (no source address to reach them)
+/// 2. Prefer matching scope of the destination and its source address
+/// 5. Prefer matching label of the destination and the source address
+/// 6. Prefer higher precedence (according to the default policy table)
+/// 8. Prefer smaller scope
+/// 9. Use longest matching prefix with the source address (only when
+///    destinations are of the same address family)
+///
+/// Otherwise original order is kept. Rules 3, 4 and 7 are skipped as
+/// there is no portable way to find out whether an address is deprecated,
+/// is a home address or uses an encapsulating transition mechanism.
+///
+/// By default source address for each destination is the one chosen by
+/// the operating system (found by "connecting" an UDP socket, which doesn't
+/// send any packets). Use `with_sources` to supply a list of the local
+/// addresses instead, e.g. for tests.
+#[derive(Debug, Clone)]
+pub struct DestinationOrder {
+    sources: Option<Vec<IpAddr>>,
+}
+
+#[derive(Debug)]
+struct Destination {
+    addr: IpAddr,
+    source: Option<IpAddr>,
+}
+
+impl DestinationOrder {
+    /// Create an ordering which uses source addresses chosen by the system
+    pub fn new() -> DestinationOrder {
+        DestinationOrder { sources: None }
+    }
+    /// Create an ordering which uses a fixed list of source addresses
+    ///
+    /// Source address for each destination is selected from the list
+    /// using a subset of rules from RFC 6724 (section 5): prefer the same
+    /// address, appropriate scope, matching label and longest matching
+    /// prefix. Destinations of the address family which is not in the list
+    /// are unusable.
+    pub fn with_sources<I>(sources: I) -> DestinationOrder
+        where I: IntoIterator<Item=IpAddr>
+    {
+        DestinationOrder { sources: Some(sources.into_iter().collect()) }
+    }
+    /// Returns sorted copy of the list
+    pub fn sort(&self, list: &IpList) -> IpList {
+        let mut dests = list.iter().map(|&addr| Destination {
+            addr,
+            source: self.source_for(addr),
+        }).collect::<Vec<_>>();
+        dests.sort_by(compare);
+        dests.into_iter().map(|d| d.addr).collect()
+    }
+    fn source_for(&self, dest: IpAddr) -> Option<IpAddr> {
+        match self.sources {
+            Some(ref sources) => {
+                sources.iter().cloned()
+                    .filter(|s| s.is_ipv4() == dest.is_ipv4())
+                    .min_by(|&a, &b| compare_sources(a, b, dest))
+            }
+            None => system_source(dest),
+        }
+    }
+}
+
+impl Default for DestinationOrder {
+    fn default() -> DestinationOrder {
+        DestinationOrder::new()
+    }
+}
+
+fn system_source(dest: IpAddr) -> Option<IpAddr> {
+    let bind: IpAddr = match dest {
+        IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    // port doesn't matter, nothing is sent
+    let sock = UdpSocket::bind(SocketAddr::new(bind, 0)).ok()?;
+    sock.connect(SocketAddr::new(dest, 9)).ok()?;
+    sock.local_addr().ok().map(|a| a.ip())
+}
+
+fn as_v6(addr: IpAddr) -> Ipv6Addr {
+    match addr {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn has_prefix(addr: Ipv6Addr, prefix: u128, len: u32) -> bool {
+    len == 0 || u128::from(addr) >> (128 - len) == prefix >> (128 - len)
+}
+
+/// Default policy table: (prefix, prefix length, precedence, label)
+const POLICY: &[(u128, u32, u8, u8)] = &[
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, 50, 0),  // ::1
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000, 96, 35, 4),   // ::ffff:0:0
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 96, 1, 3),    // ::
+    (0x2001_0000_0000_0000_0000_0000_0000_0000, 32, 5, 5),    // 2001::
+    (0x2002_0000_0000_0000_0000_0000_0000_0000, 16, 30, 2),   // 2002::
+    (0x3ffe_0000_0000_0000_0000_0000_0000_0000, 16, 1, 12),   // 3ffe::
+    (0xfec0_0000_0000_0000_0000_0000_0000_0000, 10, 1, 11),   // fec0::
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000, 7, 3, 13),    // fc00::
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 0, 40, 1),    // ::/0
+];
+
+fn policy(addr: IpAddr) -> (u8, u8) {
+    let addr = as_v6(addr);
+    POLICY.iter()
+        .find(|&&(prefix, len, _, _)| has_prefix(addr, prefix, len))
+        .map(|&(_, _, precedence, label)| (precedence, label))
+        .expect("policy table has a default entry")
+}
+
+fn precedence(addr: IpAddr) -> u8 {
+    policy(addr).0
+}
+
+fn label(addr: IpAddr) -> u8 {
+    policy(addr).1
+}
+
+const SCOPE_LINK_LOCAL: u8 = 0x2;
+const SCOPE_SITE_LOCAL: u8 = 0x5;
+const SCOPE_GLOBAL: u8 = 0xe;
+
+fn scope(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(ip) => {
+            if ip.is_loopback() || ip.is_link_local() {
+                SCOPE_LINK_LOCAL
+            } else {
+                SCOPE_GLOBAL
+            }
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            if ip.is_multicast() {
+                (segments[0] & 0x000f) as u8
+            } else if ip.is_loopback() || segments[0] & 0xffc0 == 0xfe80 {
+                SCOPE_LINK_LOCAL
+            } else if segments[0] & 0xffc0 == 0xfec0 {
+                SCOPE_SITE_LOCAL
+            } else {
+                SCOPE_GLOBAL
+            }
+        }
+    }
+}
+
+fn common_prefix_len(a: IpAddr, b: IpAddr) -> u32 {
+    match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            (u32::from(a) ^ u32::from(b)).leading_zeros()
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            // only the prefix is compared, not the interface id
+            (u128::from(a) ^ u128::from(b)).leading_zeros().min(64)
+        }
+        _ => 0,
+    }
+}
+
+/// Returns `Less` if `a` is a better destination than `b`
+fn compare(a: &Destination, b: &Destination) -> Ordering {
+    let (sa, sb) = match (a.source, b.source) {
+        // rule 1: avoid unusable destinations
+        (Some(_), None) => return Ordering::Less,
+        (None, Some(_)) => return Ordering::Greater,
+        (None, None) => return Ordering::Equal,
+        (Some(sa), Some(sb)) => (sa, sb),
+    };
+    // rule 2: prefer matching scope
+    let scope_a = scope(a.addr) == scope(sa);
+    let scope_b = scope(b.addr) == scope(sb);
+    // rule 5: prefer matching label
+    let label_a = label(a.addr) == label(sa);
+    let label_b = label(b.addr) == label(sb);
+    scope_b.cmp(&scope_a)
+    .then(label_b.cmp(&label_a))
+    // rule 6: prefer higher precedence
+    .then(precedence(b.addr).cmp(&precedence(a.addr)))
+    // rule 8: prefer smaller scope
+    .then(scope(a.addr).cmp(&scope(b.addr)))
+    // rule 9: use longest matching prefix
+    .then_with(|| if a.addr.is_ipv4() == b.addr.is_ipv4() {
+        common_prefix_len(sb, b.addr).cmp(&common_prefix_len(sa, a.addr))
+    } else {
+        Ordering::Equal
+    })
+}
+
+/// Returns `Less` if `a` is a better source address for `dest` than `b`
+fn compare_sources(a: IpAddr, b: IpAddr, dest: IpAddr) -> Ordering {
+    // rule 1: prefer same address
+    (b == dest).cmp(&(a == dest))
+    // rule 2: prefer appropriate scope
+    .then_with(|| {
+        let (scope_a, scope_b) = (scope(a), scope(b));
+        let scope_dest = scope(dest);
+        if scope_a < scope_b {
+            if scope_a < scope_dest {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        } else if scope_b < scope_a {
+            if scope_b < scope_dest {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        } else {
+            Ordering::Equal
+        }
+    })
+    // rule 6: prefer matching label
+    .then_with(|| {
+        (label(b) == label(dest)).cmp(&(label(a) == label(dest)))
+    })
+    // rule 8: use longest matching prefix
+    .then_with(|| {
+        common_prefix_len(b, dest).cmp(&common_prefix_len(a, dest))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use ip_list::IpList;
+    use super::DestinationOrder;
+
+    fn ip(x: &str) -> IpAddr {
+        x.parse().unwrap()
+    }
+
+    fn sort(sources: &[&str], dests: &[&str]) -> Vec<IpAddr> {
+        let order = DestinationOrder::with_sources(
+            sources.iter().map(|x| ip(x)));
+        order.sort(&IpList::parse_list(dests).unwrap())
+            .iter().cloned().collect()
+    }
+
+    fn ips(x: &[&str]) -> Vec<IpAddr> {
+        x.iter().map(|x| ip(x)).collect()
+    }
+
+    #[test]
+    fn prefer_ipv6() {
+        // examples from RFC 6724 section 10.2
+        assert_eq!(
+            sort(&["2001:db8:1::2", "fe80::1", "169.254.13.78"],
+                 &["198.51.100.121", "2001:db8:1::1"]),
+            ips(&["2001:db8:1::1", "198.51.100.121"]));
+    }
+
+    #[test]
+    fn unusable() {
+        assert_eq!(
+            sort(&["fe80::1", "198.51.100.117"],
+                 &["2001:db8:1::1", "198.51.100.121"]),
+            ips(&["198.51.100.121", "2001:db8:1::1"]));
+        assert_eq!(
+            sort(&["198.51.100.117"],
+                 &["2001:db8:1::1", "198.51.100.121"]),
+            ips(&["198.51.100.121", "2001:db8:1::1"]));
+    }
+
+    #[test]
+    fn smaller_scope() {
+        assert_eq!(
+            sort(&["2001:db8:1::2", "fe80::2"],
+                 &["2001:db8:1::1", "fe80::1"]),
+            ips(&["fe80::1", "2001:db8:1::1"]));
+    }
+
+    #[test]
+    fn longest_prefix() {
+        assert_eq!(
+            sort(&["2001:db8:1::2"],
+                 &["2001:db8:3ffe::1", "2001:db8:1::1"]),
+            ips(&["2001:db8:1::1", "2001:db8:3ffe::1"]));
+        // prefix is compared up to the interface id
+        assert_eq!(
+            sort(&["2001:db8:1::2"],
+                 &["2001:db8:1::3", "2001:db8:1::2:1"]),
+            ips(&["2001:db8:1::3", "2001:db8:1::2:1"]));
+    }
+
+    #[test]
+    fn precedence() {
+        // 6to4 and ULA have lower precedence than native IPv6
+        assert_eq!(
+            sort(&["2002:c633:6401::2", "fd11::2", "2001:db8:1::2"],
+                 &["fd11::1", "2002:c633:6401::1", "2001:db8:1::1"]),
+            ips(&["2001:db8:1::1", "2002:c633:6401::1", "fd11::1"]));
+        // matching label wins over precedence
+        assert_eq!(
+            sort(&["2002:c633:6401::2", "fe80::1"],
+                 &["2001:db8:1::1", "2002:c633:6403::1"]),
+            ips(&["2002:c633:6403::1", "2001:db8:1::1"]));
+    }
+
+    #[test]
+    fn ipv4_scope() {
+        assert_eq!(
+            sort(&["169.254.1.1", "198.51.100.117"],
+                 &["198.51.100.121", "169.254.2.2"]),
+            ips(&["169.254.2.2", "198.51.100.121"]));
+    }
+
+    #[test]
+    fn system_sources() {
+        let list = IpList::parse_list(&["127.0.0.1"]).unwrap();
+        assert_eq!(DestinationOrder::new().sort(&list), list);
+    }
+}
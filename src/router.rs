@@ -0,0 +1,284 @@
+//! A simple router that dispatches names to resolvers by suffix
+//!
+use std::collections::HashMap;
+use std::fmt;
+
+use futures::future::err;
+use futures::stream::once;
+use {Name, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+use combinators::{BoxFuture, BoxHostFuture, BoxStream, BoxHostStream};
+use combinators::boxed::ErasedResolver;
+
+
+/// A router that selects resolver by the suffix of the name
+///
+/// Each resolver added to the router must implement all four resolution
+/// traits. Use `null_service_resolver`, `null_host_resolver` and
+/// `frozen_subscriber` (or `interval_subscriber`) to add resolvers that
+/// support only part of the functionality.
+///
+/// Resolvers are looked up in the following order:
+///
+/// 1. Exact name added with `add_name`
+/// 2. The longest suffix added with `add_suffix`
+/// 3. Default resolver set by `set_default`
+///
+/// If nothing matches, `NameNotFound` is returned.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut router = SuffixRouter::new();
+/// router.add_suffix("consul", consul_resolver);
+/// router.add_name("localhost", hosts_resolver);
+/// router.set_default(dns_resolver);
+/// ```
+pub struct SuffixRouter {
+    names: HashMap<String, Box<dyn ErasedResolver>>,
+    suffixes: HashMap<String, Box<dyn ErasedResolver>>,
+    default: Option<Box<dyn ErasedResolver>>,
+}
+
+/// Normalizes name to be used as a key in the table
+///
+/// Names are lowercased and the final dot of the fully-qualified name
+/// is stripped, if any
+fn key(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_lowercase()
+}
+
+impl SuffixRouter {
+    /// Create an empty router
+    ///
+    /// Empty router returns `NameNotFound` for every name
+    pub fn new() -> SuffixRouter {
+        SuffixRouter {
+            names: HashMap::new(),
+            suffixes: HashMap::new(),
+            default: None,
+        }
+    }
+
+    /// Use the resolver for the exact name
+    ///
+    /// Exact names have precedence over suffixes.
+    pub fn add_name<R>(&mut self, name: &str, resolver: R) -> &mut Self
+        where R: Resolve + HostResolve + Subscribe + HostSubscribe,
+              R: Send + Sync + 'static,
+              R::Future: Send + 'static,
+              R::HostFuture: Send + 'static,
+              R::Stream: Send + 'static,
+              R::HostStream: Send + 'static,
+    {
+        self.names.insert(key(name), Box::new(resolver));
+        self
+    }
+
+    /// Use the resolver for all subdomains of the suffix
+    ///
+    /// Suffix `consul` (or `.consul`, or `*.consul`) matches
+    /// `web.service.consul` but doesn't match `consul` itself (use
+    /// `add_name` for that). When multiple suffixes match a name, the
+    /// longest one is used.
+    ///
+    /// Both names and suffixes are case-insensitive.
+    pub fn add_suffix<R>(&mut self, suffix: &str, resolver: R) -> &mut Self
+        where R: Resolve + HostResolve + Subscribe + HostSubscribe,
+              R: Send + Sync + 'static,
+              R::Future: Send + 'static,
+              R::HostFuture: Send + 'static,
+              R::Stream: Send + 'static,
+              R::HostStream: Send + 'static,
+    {
+        let suffix = suffix.strip_prefix("*.")
+            .unwrap_or_else(|| suffix.trim_start_matches('.'));
+        self.suffixes.insert(key(suffix), Box::new(resolver));
+        self
+    }
+
+    /// Use the resolver for all names that don't match any other rule
+    pub fn set_default<R>(&mut self, resolver: R) -> &mut Self
+        where R: Resolve + HostResolve + Subscribe + HostSubscribe,
+              R: Send + Sync + 'static,
+              R::Future: Send + 'static,
+              R::HostFuture: Send + 'static,
+              R::Stream: Send + 'static,
+              R::HostStream: Send + 'static,
+    {
+        self.default = Some(Box::new(resolver));
+        self
+    }
+
+    fn find(&self, name: &Name) -> Option<&dyn ErasedResolver> {
+        // names are always lowercase, so only the root is stripped here
+        let name: &str = name.as_ref();
+        let name = name.strip_suffix('.').unwrap_or(name);
+        if let Some(resolver) = self.names.get(name) {
+            return Some(&**resolver);
+        }
+        let mut suffix = name;
+        while let Some(dot) = suffix.find('.') {
+            suffix = &suffix[dot+1..];
+            if let Some(resolver) = self.suffixes.get(suffix) {
+                return Some(&**resolver);
+            }
+        }
+        self.default.as_deref()
+    }
+}
+
+impl fmt::Debug for SuffixRouter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SuffixRouter")
+        .field("names", &self.names.keys().collect::<Vec<_>>())
+        .field("suffixes", &self.suffixes.keys().collect::<Vec<_>>())
+        .field("default", &self.default.is_some())
+        .finish()
+    }
+}
+
+impl Default for SuffixRouter {
+    fn default() -> SuffixRouter {
+        SuffixRouter::new()
+    }
+}
+
+impl Resolve for SuffixRouter {
+    type Future = BoxFuture;
+    fn resolve(&self, name: &Name) -> BoxFuture {
+        match self.find(name) {
+            Some(resolver) => resolver.resolve(name),
+            None => Box::new(err(Error::NameNotFound)),
+        }
+    }
+}
+
+impl HostResolve for SuffixRouter {
+    type HostFuture = BoxHostFuture;
+    fn resolve_host(&self, name: &Name) -> BoxHostFuture {
+        match self.find(name) {
+            Some(resolver) => resolver.resolve_host(name),
+            None => Box::new(err(Error::NameNotFound)),
+        }
+    }
+}
+
+impl Subscribe for SuffixRouter {
+    type Stream = BoxStream;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> BoxStream {
+        match self.find(name) {
+            Some(resolver) => resolver.subscribe(name),
+            None => Box::new(once(Err(Error::NameNotFound))),
+        }
+    }
+}
+
+impl HostSubscribe for SuffixRouter {
+    type HostStream = BoxHostStream;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> BoxHostStream {
+        match self.find(name) {
+            Some(resolver) => resolver.subscribe_host(name),
+            None => Box::new(once(Err(Error::NameNotFound))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Future, Stream};
+    use futures::future::{FutureResult, ok};
+    use {HostResolve, Resolve, HostSubscribe, Name, IpList, Error};
+    use super::SuffixRouter;
+
+    #[derive(Debug)]
+    struct Mock(&'static str);
+
+    impl HostResolve for Mock {
+        type HostFuture = FutureResult<IpList, Error>;
+        fn resolve_host(&self, _name: &Name) -> Self::HostFuture {
+            ok(IpList::parse_list(&[self.0]).unwrap())
+        }
+    }
+
+    fn router() -> SuffixRouter {
+        let mut router = SuffixRouter::new();
+        router.add_suffix("consul",
+            Mock("127.0.0.1").null_service_resolver().frozen_subscriber());
+        router.add_suffix(".service.consul",
+            Mock("127.0.0.2").null_service_resolver().frozen_subscriber());
+        router.add_name("LocalHost",
+            Mock("127.0.0.3").null_service_resolver().frozen_subscriber());
+        router.add_suffix("*.Example.ORG.",
+            Mock("127.0.0.5").null_service_resolver().frozen_subscriber());
+        router
+    }
+
+    fn resolve(router: &SuffixRouter, name: &str) -> Result<String, Error> {
+        router.resolve_host(&name.parse().unwrap()).wait()
+            .map(|ips| ips.iter().next().unwrap().to_string())
+    }
+
+    #[test]
+    fn suffix() {
+        let router = router();
+        assert_eq!(resolve(&router, "web.consul").unwrap(), "127.0.0.1");
+        assert_eq!(resolve(&router, "web.node.consul.").unwrap(),
+                   "127.0.0.1");
+        assert_eq!(resolve(&router, "web.service.consul").unwrap(),
+                   "127.0.0.2");
+        assert_eq!(resolve(&router, "localhost").unwrap(), "127.0.0.3");
+        assert_eq!(resolve(&router, "localhost.").unwrap(), "127.0.0.3");
+    }
+
+    #[test]
+    fn wildcard_and_case() {
+        let router = router();
+        assert_eq!(resolve(&router, "www.example.org").unwrap(),
+                   "127.0.0.5");
+        assert_eq!(resolve(&router, "a.b.example.org.").unwrap(),
+                   "127.0.0.5");
+        assert_eq!(resolve(&router, "localhost").unwrap(), "127.0.0.3");
+        match resolve(&router, "example.org") {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn not_found() {
+        let router = router();
+        match resolve(&router, "consul") {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match resolve(&router, "example.org") {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match router.resolve(&"web.consul".parse().unwrap()).wait() {
+            Err(Error::NameNotFound) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn default() {
+        let mut router = router();
+        router.set_default(
+            Mock("127.0.0.4").null_service_resolver().frozen_subscriber());
+        assert_eq!(resolve(&router, "example.org").unwrap(), "127.0.0.4");
+        assert_eq!(resolve(&router, "consul").unwrap(), "127.0.0.4");
+        assert_eq!(resolve(&router, "web.consul").unwrap(), "127.0.0.1");
+    }
+
+    #[test]
+    fn subscribe() {
+        let router = router();
+        assert_eq!(router.subscribe_host(&"web.consul".parse().unwrap())
+            .wait().next().unwrap().unwrap(),
+            IpList::parse_list(&["127.0.0.1"]).unwrap());
+    }
+}
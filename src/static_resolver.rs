@@ -0,0 +1,210 @@
+//! A resolver that uses in-memory table of names
+//!
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::{Async, Stream};
+use futures::future::{FutureResult, ok, err};
+use {Name, Address, IpList, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+use watchers::Watchers;
+
+
+/// A resolver that returns addresses from an in-memory table
+///
+/// This is useful for tests and to override addresses of some names
+/// (put it in front of other resolvers using `Fallback`).
+///
+/// The table can be changed at runtime, and all subscriptions receive
+/// new addresses when they are changed. Cloned resolver shares the table
+/// with the original one, so you can keep a clone to update addresses
+/// after putting the resolver into a router.
+///
+/// Names that are not in the table are resolved to `NameNotFound`,
+/// subscriptions are also failed with `NameNotFound` when name
+/// is removed from the table.
+#[derive(Debug, Clone)]
+pub struct StaticResolver {
+    table: Arc<Mutex<Table>>,
+}
+
+/// A stream returned from `StaticResolver` subscriptions
+#[derive(Debug)]
+pub struct StaticStream<T> {
+    table: Arc<Mutex<Table>>,
+    get: fn(&Table, &Name) -> Option<T>,
+    name: Name,
+    last_value: Option<T>,
+    watcher: usize,
+}
+
+#[derive(Debug)]
+struct Table {
+    addresses: HashMap<Name, Address>,
+    hosts: HashMap<Name, IpList>,
+    watchers: Watchers,
+}
+
+impl Table {
+    fn address(&self, name: &Name) -> Option<Address> {
+        self.addresses.get(name).cloned()
+    }
+    fn host(&self, name: &Name) -> Option<IpList> {
+        self.hosts.get(name).cloned()
+    }
+}
+
+impl StaticResolver {
+    /// Create a resolver with an empty table
+    pub fn new() -> StaticResolver {
+        StaticResolver {
+            table: Arc::new(Mutex::new(Table {
+                addresses: HashMap::new(),
+                hosts: HashMap::new(),
+                watchers: Watchers::default(),
+            })),
+        }
+    }
+    fn modify<F: FnOnce(&mut Table)>(&self, f: F) {
+        let mut table = self.table.lock().expect("table is not poisoned");
+        f(&mut table);
+        table.watchers.notify();
+    }
+    /// Add or replace an address for a service name
+    pub fn insert_address(&self, name: &Name, address: Address) {
+        self.modify(|t| { t.addresses.insert(name.clone(), address); });
+    }
+    /// Add or replace a list of IP addresses for a host name
+    pub fn insert_host(&self, name: &Name, ips: IpList) {
+        self.modify(|t| { t.hosts.insert(name.clone(), ips); });
+    }
+    /// Remove service name from the table
+    pub fn remove_address(&self, name: &Name) {
+        self.modify(|t| { t.addresses.remove(name); });
+    }
+    /// Remove host name from the table
+    pub fn remove_host(&self, name: &Name) {
+        self.modify(|t| { t.hosts.remove(name); });
+    }
+    /// Replace the whole table at once
+    ///
+    /// Subscribers only receive updates for names which are changed
+    pub fn replace(&self, addresses: HashMap<Name, Address>,
+                   hosts: HashMap<Name, IpList>)
+    {
+        self.modify(|t| {
+            t.addresses = addresses;
+            t.hosts = hosts;
+        });
+    }
+    fn stream<T>(&self, name: &Name, get: fn(&Table, &Name) -> Option<T>)
+        -> StaticStream<T>
+    {
+        let watcher = self.table.lock().expect("table is not poisoned")
+            .watchers.add();
+        StaticStream {
+            table: self.table.clone(),
+            get,
+            name: name.clone(),
+            last_value: None,
+            watcher,
+        }
+    }
+}
+
+impl Default for StaticResolver {
+    fn default() -> StaticResolver {
+        StaticResolver::new()
+    }
+}
+
+impl Resolve for StaticResolver {
+    type Future = FutureResult<Address, Error>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        let table = self.table.lock().expect("table is not poisoned");
+        match table.address(name) {
+            Some(addr) => ok(addr),
+            None => err(Error::NameNotFound),
+        }
+    }
+}
+
+impl HostResolve for StaticResolver {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        let table = self.table.lock().expect("table is not poisoned");
+        match table.host(name) {
+            Some(ips) => ok(ips),
+            None => err(Error::NameNotFound),
+        }
+    }
+}
+
+impl Subscribe for StaticResolver {
+    type Stream = StaticStream<Address>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        self.stream(name, Table::address)
+    }
+}
+
+impl HostSubscribe for StaticResolver {
+    type HostStream = StaticStream<IpList>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.stream(name, Table::host)
+    }
+}
+
+impl<T: PartialEq + Clone> Stream for StaticStream<T> {
+    type Item = T;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<T>>, Error> {
+        let mut table = self.table.lock().expect("table is not poisoned");
+        match (self.get)(&table, &self.name) {
+            None => Err(Error::NameNotFound),
+            Some(ref value) if self.last_value.as_ref() == Some(value) => {
+                table.watchers.park(self.watcher);
+                Ok(Async::NotReady)
+            }
+            Some(value) => {
+                self.last_value = Some(value.clone());
+                Ok(Async::Ready(Some(value)))
+            }
+        }
+    }
+}
+
+impl<T> Drop for StaticStream<T> {
+    fn drop(&mut self) {
+        if let Ok(mut table) = self.table.lock() {
+            table.watchers.remove(self.watcher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Async, Stream};
+    use futures::future::{lazy, Future};
+    use Subscribe;
+    use super::StaticResolver;
+
+    #[test]
+    fn watchers_are_not_leaked() {
+        let name = "example.org".parse().unwrap();
+        let r = StaticResolver::new();
+        r.insert_address(&name, "[127.0.0.1:80]".parse().unwrap());
+        let mut s = r.subscribe(&name);
+        lazy(|| {
+            assert!(s.poll().unwrap().is_ready());
+            for _ in 0..10 {
+                assert_eq!(s.poll().unwrap(), Async::NotReady);
+            }
+            Ok::<(), ()>(())
+        }).wait().unwrap();
+        assert_eq!(r.table.lock().unwrap().watchers.len(), 1);
+        drop(s);
+        assert_eq!(r.table.lock().unwrap().watchers.len(), 0);
+    }
+}
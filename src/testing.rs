@@ -0,0 +1,331 @@
+//! Utilities for testing code that uses resolvers
+//!
+//! This module is only available with the `testing` feature enabled.
+//!
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Stream};
+use tokio_timer::Delay;
+use {Name, Address, IpList, Error};
+use {Resolve, Subscribe, HostResolve, HostSubscribe};
+use watchers::Watchers;
+
+
+/// A single response programmed into `MockResolver`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply<T> {
+    /// Resolve the name successfully
+    Value(T),
+    /// Fail with `Error::NameNotFound`
+    NameNotFound,
+    /// Fail with `Error::TemporaryError` with the specified message
+    TemporaryError(String),
+    /// Fail with `Error::Timeout`
+    Timeout,
+    /// Never resolve (and never yield anything after this in the script)
+    Hang,
+    /// Wait for the specified time before replying
+    ///
+    /// This requires a timer, i.e. running in a tokio runtime.
+    Delayed(Duration, Box<Reply<T>>),
+}
+
+/// A call made to the `MockResolver`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    /// `Resolve::resolve`
+    Resolve(Name),
+    /// `HostResolve::resolve_host`
+    ResolveHost(Name),
+    /// `Subscribe::subscribe`
+    Subscribe(Name),
+    /// `HostSubscribe::subscribe_host`
+    SubscribeHost(Name),
+}
+
+/// A resolver which returns programmed responses
+///
+/// For `resolve` and `resolve_host` a single reply is programmed per name
+/// and it's returned on every call. Subscriptions play a script: a list of
+/// replies which are yielded in order, every subscriber plays the whole
+/// script from the beginning. Values can be appended to the script while
+/// there are active subscriptions, so tests can drive address changes.
+/// When subscriber reaches the end of the script it waits for more values.
+///
+/// Names that have no reply programmed are resolved to `NameNotFound`,
+/// subscriptions to such names wait for the first value in the script.
+///
+/// All calls are recorded and can be inspected with `calls()`.
+///
+/// Cloned resolver shares all the state with the original one.
+#[derive(Debug, Clone)]
+pub struct MockResolver {
+    state: Arc<Mutex<State>>,
+}
+
+/// A future returned by `MockResolver`
+#[derive(Debug)]
+pub struct MockFuture<T> {
+    reply: Option<Reply<T>>,
+    timer: Option<Delay>,
+}
+
+/// A stream returned by `MockResolver` subscriptions
+#[derive(Debug)]
+pub struct MockStream<T> {
+    state: Arc<Mutex<State>>,
+    get: fn(&State, &Name, usize) -> Option<Reply<T>>,
+    name: Name,
+    index: usize,
+    current: Option<MockFuture<T>>,
+    watcher: usize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    addresses: HashMap<Name, Reply<Address>>,
+    hosts: HashMap<Name, Reply<IpList>>,
+    address_scripts: HashMap<Name, Vec<Reply<Address>>>,
+    host_scripts: HashMap<Name, Vec<Reply<IpList>>>,
+    calls: Vec<Call>,
+    watchers: Watchers,
+}
+
+impl<T> From<T> for Reply<T> {
+    fn from(value: T) -> Reply<T> {
+        Reply::Value(value)
+    }
+}
+
+impl<T> Reply<T> {
+    /// Delay this reply for the specified time
+    pub fn delayed(self, delay: Duration) -> Reply<T> {
+        Reply::Delayed(delay, Box::new(self))
+    }
+}
+
+impl State {
+    fn address_step(&self, name: &Name, idx: usize)
+        -> Option<Reply<Address>>
+    {
+        self.address_scripts.get(name).and_then(|s| s.get(idx)).cloned()
+    }
+    fn host_step(&self, name: &Name, idx: usize) -> Option<Reply<IpList>> {
+        self.host_scripts.get(name).and_then(|s| s.get(idx)).cloned()
+    }
+}
+
+impl MockResolver {
+    /// Create a resolver with nothing programmed
+    pub fn new() -> MockResolver {
+        MockResolver {
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("mock state is not poisoned")
+    }
+    /// Set the reply for `resolve` of the name
+    pub fn set_address<R>(&self, name: &Name, reply: R)
+        where R: Into<Reply<Address>>
+    {
+        self.lock().addresses.insert(name.clone(), reply.into());
+    }
+    /// Set the reply for `resolve_host` of the name
+    pub fn set_host<R>(&self, name: &Name, reply: R)
+        where R: Into<Reply<IpList>>
+    {
+        self.lock().hosts.insert(name.clone(), reply.into());
+    }
+    /// Append a reply to the subscription script of the name
+    pub fn push_address<R>(&self, name: &Name, reply: R)
+        where R: Into<Reply<Address>>
+    {
+        let mut state = self.lock();
+        state.address_scripts.entry(name.clone()).or_default()
+            .push(reply.into());
+        state.watchers.notify();
+    }
+    /// Append a reply to the host subscription script of the name
+    pub fn push_host<R>(&self, name: &Name, reply: R)
+        where R: Into<Reply<IpList>>
+    {
+        let mut state = self.lock();
+        state.host_scripts.entry(name.clone()).or_default()
+            .push(reply.into());
+        state.watchers.notify();
+    }
+    /// Returns all the calls made to this resolver so far
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+    /// Clear the log of calls
+    pub fn clear_calls(&self) {
+        self.lock().calls.clear();
+    }
+    fn stream<T>(&self, name: &Name,
+        get: fn(&State, &Name, usize) -> Option<Reply<T>>)
+        -> MockStream<T>
+    {
+        MockStream {
+            state: self.state.clone(),
+            get,
+            name: name.clone(),
+            index: 0,
+            current: None,
+            watcher: self.lock().watchers.add(),
+        }
+    }
+}
+
+impl Default for MockResolver {
+    fn default() -> MockResolver {
+        MockResolver::new()
+    }
+}
+
+impl<T> MockFuture<T> {
+    fn new(reply: Option<Reply<T>>) -> MockFuture<T> {
+        MockFuture {
+            reply: Some(reply.unwrap_or(Reply::NameNotFound)),
+            timer: None,
+        }
+    }
+}
+
+impl Resolve for MockResolver {
+    type Future = MockFuture<Address>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        let mut state = self.lock();
+        state.calls.push(Call::Resolve(name.clone()));
+        MockFuture::new(state.addresses.get(name).cloned())
+    }
+}
+
+impl HostResolve for MockResolver {
+    type HostFuture = MockFuture<IpList>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        let mut state = self.lock();
+        state.calls.push(Call::ResolveHost(name.clone()));
+        MockFuture::new(state.hosts.get(name).cloned())
+    }
+}
+
+impl Subscribe for MockResolver {
+    type Stream = MockStream<Address>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        self.lock().calls.push(Call::Subscribe(name.clone()));
+        self.stream(name, State::address_step)
+    }
+}
+
+impl HostSubscribe for MockResolver {
+    type HostStream = MockStream<IpList>;
+    type HostError = Error;
+    fn subscribe_host(&self, name: &Name) -> Self::HostStream {
+        self.lock().calls.push(Call::SubscribeHost(name.clone()));
+        self.stream(name, State::host_step)
+    }
+}
+
+impl<T> Future for MockFuture<T> {
+    type Item = T;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<T>, Error> {
+        loop {
+            if let Some(mut timer) = self.timer.take() {
+                match timer.poll() {
+                    Ok(Async::Ready(())) => {}
+                    Ok(Async::NotReady) => {
+                        self.timer = Some(timer);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => return Err(Error::TemporaryError(Box::new(e))),
+                }
+            }
+            match self.reply.take().expect("future polled after completion") {
+                Reply::Value(value) => return Ok(Async::Ready(value)),
+                Reply::NameNotFound => return Err(Error::NameNotFound),
+                Reply::TemporaryError(msg) => {
+                    return Err(Error::TemporaryError(msg.into()));
+                }
+                Reply::Timeout => return Err(Error::Timeout),
+                Reply::Hang => {
+                    self.reply = Some(Reply::Hang);
+                    return Ok(Async::NotReady);
+                }
+                Reply::Delayed(delay, reply) => {
+                    self.timer = Some(Delay::new(Instant::now() + delay));
+                    self.reply = Some(*reply);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Stream for MockStream<T> {
+    type Item = T;
+    type Error = Error;
+    fn poll(&mut self) -> Result<Async<Option<T>>, Error> {
+        loop {
+            if let Some(ref mut future) = self.current {
+                let value = match future.poll()? {
+                    Async::Ready(value) => value,
+                    Async::NotReady => return Ok(Async::NotReady),
+                };
+                self.current = None;
+                self.index += 1;
+                return Ok(Async::Ready(Some(value)));
+            }
+            let mut state = self.state.lock()
+                .expect("mock state is not poisoned");
+            match (self.get)(&state, &self.name, self.index) {
+                Some(reply) => {
+                    self.current = Some(MockFuture {
+                        reply: Some(reply),
+                        timer: None,
+                    });
+                }
+                None => {
+                    state.watchers.park(self.watcher);
+                    return Ok(Async::NotReady);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for MockStream<T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.watchers.remove(self.watcher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Async, Stream};
+    use futures::future::{lazy, Future};
+    use Subscribe;
+    use super::MockResolver;
+
+    #[test]
+    fn watchers_are_not_leaked() {
+        let name = "example.org".parse().unwrap();
+        let r = MockResolver::new();
+        let mut s = r.subscribe(&name);
+        lazy(|| {
+            for _ in 0..10 {
+                assert_eq!(s.poll().unwrap(), Async::NotReady);
+            }
+            Ok::<(), ()>(())
+        }).wait().unwrap();
+        assert_eq!(r.lock().watchers.len(), 1);
+        drop(s);
+        assert_eq!(r.lock().watchers.len(), 0);
+    }
+}
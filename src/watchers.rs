@@ -0,0 +1,52 @@
+//! Tasks waiting for changes of a shared in-memory table
+//!
+use std::collections::HashMap;
+
+use futures::task::{self, Task};
+
+
+/// A set of tasks to wake up when the table is changed
+///
+/// Every stream registers once and gets an id, so repeated polls of the
+/// same stream replace its task instead of adding one more. Stream must
+/// call `remove` when it's dropped.
+#[derive(Debug, Default)]
+pub struct Watchers {
+    next_id: usize,
+    tasks: HashMap<usize, Option<Task>>,
+}
+
+impl Watchers {
+    /// Register a new stream, returns id of the stream
+    pub fn add(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.tasks.insert(id, None);
+        id
+    }
+    /// Remember current task to be woken up on the next change
+    pub fn park(&mut self, id: usize) {
+        if let Some(slot) = self.tasks.get_mut(&id) {
+            match *slot {
+                Some(ref task) if task.will_notify_current() => {}
+                _ => *slot = Some(task::current()),
+            }
+        }
+    }
+    /// Unregister the stream
+    pub fn remove(&mut self, id: usize) {
+        self.tasks.remove(&id);
+    }
+    /// Wake up all parked tasks
+    pub fn notify(&mut self) {
+        for slot in self.tasks.values_mut() {
+            if let Some(task) = slot.take() {
+                task.notify();
+            }
+        }
+    }
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+}
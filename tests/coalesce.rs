@@ -0,0 +1,116 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use futures::Future;
+use futures::future::{Flatten, MapErr};
+use futures::sync::oneshot::{channel, Sender, Receiver, Canceled};
+use abstract_ns::{HostResolve, Name, IpList, Error};
+use abstract_ns::combinators::CoalescingResolver;
+
+
+/// Resolver which answers only when `reply` is called
+type Reply = Receiver<Result<IpList, Error>>;
+type Request = (Name, Sender<Result<IpList, Error>>);
+
+#[derive(Debug)]
+struct Manual {
+    requests: Mutex<Vec<Request>>,
+}
+
+fn canceled(_: Canceled) -> Error {
+    Error::NameNotFound
+}
+
+impl HostResolve for Manual {
+    type HostFuture = Flatten<MapErr<Reply, fn(Canceled) -> Error>>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        let (tx, rx) = channel();
+        self.requests.lock().unwrap().push((name.clone(), tx));
+        rx.map_err(canceled as fn(Canceled) -> Error).flatten()
+    }
+}
+
+impl Manual {
+    fn new() -> Arc<Manual> {
+        Arc::new(Manual { requests: Mutex::new(Vec::new()) })
+    }
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().iter()
+            .map(|(name, _)| name.to_string()).collect()
+    }
+    fn reply(&self, name: &str, value: Result<IpList, Error>) {
+        let mut requests = self.requests.lock().unwrap();
+        let idx = requests.iter().position(|(n, _)| n.as_ref() == name)
+            .unwrap();
+        requests.remove(idx).1.send(value).unwrap();
+    }
+}
+
+fn ips(ip: &str) -> IpList {
+    IpList::parse_list(&[ip]).unwrap()
+}
+
+#[test]
+fn test_coalesce() {
+    let manual = Manual::new();
+    let r = CoalescingResolver::new(manual.clone());
+    let f1 = r.resolve_host(&"localhost".parse().unwrap());
+    let f2 = r.resolve_host(&"localhost".parse().unwrap());
+    let f3 = r.resolve_host(&"example.org".parse().unwrap());
+    assert_eq!(manual.requests(), vec!["localhost", "example.org"]);
+    manual.reply("example.org", Ok(ips("127.0.0.2")));
+    manual.reply("localhost", Ok(ips("127.0.0.1")));
+    assert_eq!(f1.wait().unwrap(), ips("127.0.0.1"));
+    assert_eq!(f2.wait().unwrap(), ips("127.0.0.1"));
+    assert_eq!(f3.wait().unwrap(), ips("127.0.0.2"));
+}
+
+#[test]
+fn test_new_request_after_completion() {
+    let manual = Manual::new();
+    let r = CoalescingResolver::new(manual.clone());
+    let f1 = r.resolve_host(&"localhost".parse().unwrap());
+    manual.reply("localhost", Ok(ips("127.0.0.1")));
+    assert_eq!(f1.wait().unwrap(), ips("127.0.0.1"));
+    let f2 = r.resolve_host(&"localhost".parse().unwrap());
+    assert_eq!(manual.requests(), vec!["localhost"]);
+    manual.reply("localhost", Ok(ips("127.0.0.2")));
+    assert_eq!(f2.wait().unwrap(), ips("127.0.0.2"));
+}
+
+#[test]
+fn test_shared_error() {
+    let manual = Manual::new();
+    let r = CoalescingResolver::new(manual.clone());
+    let f1 = r.resolve_host(&"localhost".parse().unwrap());
+    let f2 = r.resolve_host(&"localhost".parse().unwrap());
+    manual.reply("localhost", Err(Error::TemporaryError(Box::new(
+        io::Error::new(io::ErrorKind::ConnectionRefused, "oh no!")))));
+    for f in &mut [f1, f2] {
+        match f.wait() {
+            Err(Error::TemporaryError(e)) => {
+                assert_eq!(e.to_string(), "oh no!");
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_new_request_after_drop() {
+    let manual = Manual::new();
+    let r = CoalescingResolver::new(manual.clone());
+    let f1 = r.resolve_host(&"localhost".parse().unwrap());
+    let f2 = r.resolve_host(&"localhost".parse().unwrap());
+    drop(f1);
+    drop(f2);
+    let stale = manual.requests.lock().unwrap().remove(0);
+    assert!(stale.1.is_canceled());
+    let f3 = r.resolve_host(&"localhost".parse().unwrap());
+    assert_eq!(manual.requests(), vec!["localhost"]);
+    manual.reply("localhost", Ok(ips("127.0.0.2")));
+    assert_eq!(f3.wait().unwrap(), ips("127.0.0.2"));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature="connect")]
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::net::{TcpListener, SocketAddr};
+
+use tokio_core::reactor::Core;
+use abstract_ns::addr::Builder;
+use abstract_ns::connect::HappyEyeballs;
+
+
+fn closed_port() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+#[test]
+fn fallback_to_lower_priority() {
+    let mut core = Core::new().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let good = listener.local_addr().unwrap();
+    let bad = closed_port();
+    let mut builder = Builder::new();
+    builder.add_addresses(&[(1, bad)]);
+    builder.add_addresses(&[(1, good)]);
+    let conn = core.run(HappyEyeballs::new().connect(&builder.into_address()))
+        .unwrap();
+    assert_eq!(conn.addr, good);
+    assert_eq!(conn.errors.len(), 1);
+    assert_eq!(conn.errors[0].0, bad);
+}
+
+#[test]
+fn all_failed() {
+    let mut core = Core::new().unwrap();
+    let (a, b) = (closed_port(), closed_port());
+    let mut builder = Builder::new();
+    builder.add_addresses(&[(1, a), (1, b)]);
+    let err = core.run(HappyEyeballs::new().connect(&builder.into_address()))
+        .unwrap_err();
+    let mut addrs = err.errors().iter().map(|&(a, _)| a).collect::<Vec<_>>();
+    addrs.sort();
+    let mut expected = vec![a, b];
+    expected.sort();
+    assert_eq!(addrs, expected);
+}
+
+#[test]
+fn empty_address() {
+    let mut core = Core::new().unwrap();
+    let err = core.run(HappyEyeballs::new()
+        .connect(&Builder::new().into_address()))
+        .unwrap_err();
+    assert!(err.errors().is_empty());
+    assert_eq!(err.to_string(), "no addresses to connect to");
+}
@@ -0,0 +1,153 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use std::io;
+
+use futures::{Future, Stream};
+use futures::future::{FutureResult, ok, err};
+use abstract_ns::{HostResolve, Resolve, Name, Address, IpList, Error};
+use abstract_ns::{Subscribe, HostSubscribe};
+use abstract_ns::combinators::{Fallback, FallbackChain};
+
+
+#[derive(Debug)]
+enum Mock {
+    Found(&'static str),
+    NotFound,
+    Temporary,
+    Invalid,
+}
+
+impl Mock {
+    fn result(&self, name: &Name) -> Result<&'static str, Error> {
+        match *self {
+            Mock::Found(ip) => Ok(ip),
+            Mock::NotFound => Err(Error::NameNotFound),
+            Mock::Temporary => Err(Error::TemporaryError(Box::new(
+                io::Error::new(io::ErrorKind::ConnectionRefused, "oh no!")))),
+            Mock::Invalid => Err(Error::InvalidName(name.to_string(), "mock")),
+        }
+    }
+}
+
+impl HostResolve for Mock {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        match self.result(name) {
+            Ok(ip) => ok(IpList::parse_list(&[ip]).unwrap()),
+            Err(e) => err(e),
+        }
+    }
+}
+
+impl Resolve for Mock {
+    type Future = FutureResult<Address, Error>;
+    fn resolve(&self, name: &Name) -> Self::Future {
+        match self.result(name) {
+            Ok(ip) => ok(IpList::parse_list(&[ip]).unwrap().with_port(80)),
+            Err(e) => err(e),
+        }
+    }
+}
+
+fn name() -> Name {
+    "localhost".parse().unwrap()
+}
+
+fn all_traits<T: Resolve + HostResolve + Subscribe + HostSubscribe>(_: &T) { }
+
+#[test]
+fn test_primary() {
+    let r = Fallback::new(Mock::Found("127.0.0.1"), Mock::Found("127.0.0.2"));
+    assert_eq!(r.resolve_host(&name()).wait().unwrap(),
+               IpList::parse_list(&["127.0.0.1"]).unwrap());
+    assert_eq!(r.resolve(&name()).wait().unwrap(),
+               Address::parse_list(&["127.0.0.1:80"]).unwrap());
+}
+
+#[test]
+fn test_not_found() {
+    let r = Fallback::new(Mock::NotFound, Mock::Found("127.0.0.2"));
+    assert_eq!(r.resolve_host(&name()).wait().unwrap(),
+               IpList::parse_list(&["127.0.0.2"]).unwrap());
+    assert_eq!(r.resolve(&name()).wait().unwrap(),
+               Address::parse_list(&["127.0.0.2:80"]).unwrap());
+}
+
+#[test]
+fn test_temporary() {
+    let mut r = Fallback::new(Mock::Temporary, Mock::Found("127.0.0.2"));
+    match r.resolve_host(&name()).wait() {
+        Err(Error::TemporaryError(_)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    r.fallback_on_temporary(true);
+    assert_eq!(r.resolve_host(&name()).wait().unwrap(),
+               IpList::parse_list(&["127.0.0.2"]).unwrap());
+}
+
+#[test]
+fn test_invalid_name() {
+    let mut r = Fallback::new(Mock::Invalid, Mock::Found("127.0.0.2"));
+    r.fallback_on_temporary(true);
+    match r.resolve(&name()).wait() {
+        Err(Error::InvalidName(..)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_subscribe() {
+    let r = Fallback::new(
+        Mock::NotFound.frozen_subscriber(),
+        Mock::Found("127.0.0.2").frozen_subscriber());
+    all_traits(&r);
+    assert_eq!(r.subscribe(&name()).wait().next().unwrap().unwrap(),
+               Address::parse_list(&["127.0.0.2:80"]).unwrap());
+    assert_eq!(r.subscribe_host(&name()).wait().next().unwrap().unwrap(),
+               IpList::parse_list(&["127.0.0.2"]).unwrap());
+}
+
+#[test]
+fn test_chain() {
+    let mut r = FallbackChain::new(vec![
+        Mock::NotFound,
+        Mock::Temporary,
+        Mock::Found("127.0.0.3"),
+        Mock::Found("127.0.0.4"),
+    ]);
+    match r.resolve_host(&name()).wait() {
+        Err(Error::TemporaryError(_)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    r.fallback_on_temporary(true);
+    assert_eq!(r.resolve_host(&name()).wait().unwrap(),
+               IpList::parse_list(&["127.0.0.3"]).unwrap());
+    assert_eq!(r.resolve(&name()).wait().unwrap(),
+               Address::parse_list(&["127.0.0.3:80"]).unwrap());
+}
+
+#[test]
+fn test_chain_exhausted() {
+    let r = FallbackChain::new(vec![Mock::NotFound, Mock::NotFound]);
+    match r.resolve(&name()).wait() {
+        Err(Error::NameNotFound) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    let r = FallbackChain::<Mock>::new(vec![]);
+    match r.resolve(&name()).wait() {
+        Err(Error::NameNotFound) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_chain_subscribe() {
+    let r = FallbackChain::new(vec![
+        Mock::NotFound.frozen_subscriber(),
+        Mock::Found("127.0.0.3").frozen_subscriber(),
+    ]);
+    all_traits(&r);
+    assert_eq!(r.subscribe_host(&name()).wait().next().unwrap().unwrap(),
+               IpList::parse_list(&["127.0.0.3"]).unwrap());
+}
@@ -0,0 +1,56 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use futures::{Future, Stream};
+use abstract_ns::{Resolve, HostResolve, HostSubscribe, Name, IpList};
+use abstract_ns::combinators::{FamilyFilter, FamilyPolicy};
+use abstract_ns::static_resolver::StaticResolver;
+
+
+fn name(x: &str) -> Name {
+    x.parse().unwrap()
+}
+
+fn resolver() -> StaticResolver {
+    let res = StaticResolver::new();
+    res.insert_host(&name("example.org"),
+        IpList::parse_list(&["127.0.0.1", "::1"]).unwrap());
+    res.insert_address(&name("_http._tcp.example.org"),
+        "[127.0.0.1:80, [::1]:80]".parse().unwrap());
+    res
+}
+
+#[test]
+fn resolve() {
+    let filter = FamilyFilter::new(resolver(), FamilyPolicy::Ipv6Only);
+    assert_eq!(filter.resolve_host(&name("example.org")).wait().unwrap(),
+               IpList::parse_list(&["::1"]).unwrap());
+    assert_eq!(filter.resolve(&name("_http._tcp.example.org"))
+               .wait().unwrap(),
+               "[[::1]:80]".parse().unwrap());
+}
+
+#[test]
+fn subscribe_skips_duplicates() {
+    let res = resolver();
+    let filter = FamilyFilter::new(res.clone(), FamilyPolicy::Ipv4Only);
+    let mut stream = filter.subscribe_host(&name("example.org")).wait();
+    assert_eq!(stream.next().unwrap().unwrap(),
+               IpList::parse_list(&["127.0.0.1"]).unwrap());
+    // only the IPv6 address is changed, nothing is yielded
+    res.insert_host(&name("example.org"),
+        IpList::parse_list(&["127.0.0.1", "::2"]).unwrap());
+    res.insert_host(&name("example.org"),
+        IpList::parse_list(&["127.0.0.2", "::2"]).unwrap());
+    assert_eq!(stream.next().unwrap().unwrap(),
+               IpList::parse_list(&["127.0.0.2"]).unwrap());
+}
+
+#[test]
+fn addr_config() {
+    let filter = FamilyFilter::new(resolver(), FamilyPolicy::AddrConfig);
+    let ips = filter.resolve_host(&name("example.org")).wait().unwrap();
+    // depends on the host, but nothing new may appear
+    assert!(ips.iter().all(|ip| ip.to_string() == "127.0.0.1" ||
+                                ip.to_string() == "::1"));
+}
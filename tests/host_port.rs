@@ -0,0 +1,60 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use futures::Future;
+use abstract_ns::{Error, IpList, Name, HostResolve};
+use abstract_ns::name::HostPort;
+use abstract_ns::static_resolver::StaticResolver;
+
+
+fn resolve(res: &StaticResolver, target: &str) -> Result<String, Error> {
+    target.parse::<HostPort>().unwrap()
+        .resolve(res).wait()
+        .map(|addr| addr.to_string())
+}
+
+#[test]
+fn resolve_all_forms() {
+    let res = StaticResolver::new();
+    let name = "example.org".parse().unwrap();
+    res.insert_host(&name, IpList::parse_list(&["127.0.0.1"]).unwrap());
+    res.insert_address(&name, "[127.0.0.2:8080]".parse().unwrap());
+    assert_eq!(resolve(&res, "example.org").unwrap(), "[127.0.0.2:8080]");
+    assert_eq!(resolve(&res, "example.org:80").unwrap(), "[127.0.0.1:80]");
+    assert_eq!(resolve(&res, "[::1]:80").unwrap(), "[[::1]:80]");
+    match resolve(&res, "127.0.0.1") {
+        Err(Error::NoDefaultPort) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match resolve(&res, "example.com:80") {
+        Err(Error::NameNotFound) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+struct HostOnly(StaticResolver);
+
+impl HostResolve for HostOnly {
+    type HostFuture = <StaticResolver as HostResolve>::HostFuture;
+    fn resolve_host(&self, name: &Name) -> Self::HostFuture {
+        self.0.resolve_host(name)
+    }
+}
+
+#[test]
+fn resolve_host_only() {
+    let res = HostOnly(StaticResolver::new());
+    let name = "example.org".parse().unwrap();
+    res.0.insert_host(&name, IpList::parse_list(&["127.0.0.1"]).unwrap());
+    let resolve = |target: &str| {
+        target.parse::<HostPort>().unwrap()
+            .resolve_host(&res).wait()
+            .map(|addr| addr.to_string())
+    };
+    assert_eq!(resolve("example.org:80").unwrap(), "[127.0.0.1:80]");
+    assert_eq!(resolve("[::1]:80").unwrap(), "[[::1]:80]");
+    match resolve("example.org") {
+        Err(Error::NoDefaultPort) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
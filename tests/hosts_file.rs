@@ -0,0 +1,79 @@
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use tokio_core::reactor::Core;
+use abstract_ns::{HostResolve, HostSubscribe, IpList, Error};
+use abstract_ns::hosts_file::HostsFileResolver;
+
+
+fn temp_file(name: &str, data: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("abstract-ns-{}-{}",
+        name, std::process::id()));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn ips(x: &[&str]) -> IpList {
+    IpList::parse_list(x).unwrap()
+}
+
+#[test]
+fn resolve() {
+    let path = temp_file("resolve", "127.0.0.1 localhost\n::1 localhost\n");
+    let r = HostsFileResolver::with_path(&path);
+    assert_eq!(r.resolve_host(&"localhost".parse().unwrap()).wait().unwrap(),
+               ips(&["127.0.0.1", "::1"]));
+    assert_eq!(r.resolve_host(&"localhost.".parse().unwrap()).wait().unwrap(),
+               ips(&["127.0.0.1", "::1"]));
+    match r.resolve_host(&"example.org".parse().unwrap()).wait() {
+        Err(Error::NameNotFound) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    fs::write(&path, "127.0.0.1 localhost example.org\n").unwrap();
+    assert_eq!(r.resolve_host(&"example.org".parse().unwrap()).wait().unwrap(),
+               ips(&["127.0.0.1"]));
+    fs::remove_file(&path).unwrap();
+    match r.resolve_host(&"localhost".parse().unwrap()).wait() {
+        Err(Error::NameNotFound) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn subscribe() {
+    let mut core = Core::new().unwrap();
+    let path = temp_file("subscribe", "127.0.0.1 localhost\n");
+    let mut r = HostsFileResolver::with_path(&path);
+    r.poll_interval(Duration::from_millis(10));
+    let stream = r.subscribe_host(&"localhost".parse().unwrap());
+    let (value, stream) = core.run(stream.into_future())
+        .map_err(|(e, _)| e).unwrap();
+    assert_eq!(value, Some(ips(&["127.0.0.1"])));
+    let writer = thread::spawn({
+        let path = path.clone();
+        move || {
+            thread::sleep(Duration::from_millis(50));
+            fs::write(&path, "127.0.0.2 localhost\n::1 localhost\n").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            fs::write(&path, "127.0.0.1 example.org\n").unwrap();
+        }
+    });
+    let (value, stream) = core.run(stream.into_future())
+        .map_err(|(e, _)| e).unwrap();
+    assert_eq!(value, Some(ips(&["127.0.0.2", "::1"])));
+    match core.run(stream.into_future()) {
+        Err((Error::NameNotFound, _)) => {}
+        Err((e, _)) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("unexpected value"),
+    }
+    writer.join().unwrap();
+    fs::remove_file(&path).unwrap();
+}
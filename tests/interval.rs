@@ -0,0 +1,147 @@
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+extern crate tokio_timer;
+
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream};
+use futures::future::{FutureResult, Either, ok, err};
+use tokio_core::reactor::Core;
+use tokio_timer::Delay;
+use abstract_ns::{HostResolve, Resolve, Name, Address, IpList, Error};
+use abstract_ns::{Subscribe, HostSubscribe};
+
+
+/// Returns values from the list one by one, repeating the last one
+#[derive(Debug)]
+struct Sequence {
+    values: Mutex<Vec<Result<&'static str, Error>>>,
+}
+
+fn sequence(values: Vec<Result<&'static str, Error>>) -> Sequence {
+    Sequence { values: Mutex::new(values) }
+}
+
+fn temporary() -> Error {
+    Error::TemporaryError(Box::new(
+        io::Error::new(io::ErrorKind::ConnectionRefused, "oh no!")))
+}
+
+impl Sequence {
+    fn next(&self) -> Result<&'static str, Error> {
+        let mut values = self.values.lock().unwrap();
+        if values.len() > 1 {
+            values.remove(0)
+        } else {
+            match values[0] {
+                Ok(x) => Ok(x),
+                Err(Error::NameNotFound) => Err(Error::NameNotFound),
+                Err(_) => Err(temporary()),
+            }
+        }
+    }
+}
+
+impl HostResolve for Sequence {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, _name: &Name) -> Self::HostFuture {
+        match self.next() {
+            Ok(x) => ok(IpList::parse_list(&[x]).unwrap()),
+            Err(e) => err(e),
+        }
+    }
+}
+
+impl Resolve for Sequence {
+    type Future = FutureResult<Address, Error>;
+    fn resolve(&self, _name: &Name) -> Self::Future {
+        match self.next() {
+            Ok(x) => ok(Address::parse_list(&[x]).unwrap()),
+            Err(e) => err(e),
+        }
+    }
+}
+
+#[test]
+fn test_changes() {
+    let mut core = Core::new().unwrap();
+    let sub = sequence(vec![
+            Ok("127.0.0.1:80"),
+            Ok("127.0.0.1:80"),
+            Ok("127.0.0.2:80"),
+        ]).interval_subscriber(Duration::from_millis(10));
+    let values = core.run(
+        sub.subscribe(&"localhost".parse().unwrap()).take(2).collect()
+    ).unwrap();
+    assert_eq!(values, vec![
+        Address::parse_list(&["127.0.0.1:80"]).unwrap(),
+        Address::parse_list(&["127.0.0.2:80"]).unwrap(),
+    ]);
+}
+
+#[test]
+fn test_host_changes() {
+    let mut core = Core::new().unwrap();
+    let sub = sequence(vec![
+            Ok("127.0.0.1"),
+            Ok("127.0.0.2"),
+            Ok("127.0.0.2"),
+            Ok("127.0.0.3"),
+        ]).interval_host_subscriber(Duration::from_millis(10));
+    let values = core.run(
+        sub.subscribe_host(&"localhost".parse().unwrap()).take(3).collect()
+    ).unwrap();
+    assert_eq!(values, vec![
+        IpList::parse_list(&["127.0.0.1"]).unwrap(),
+        IpList::parse_list(&["127.0.0.2"]).unwrap(),
+        IpList::parse_list(&["127.0.0.3"]).unwrap(),
+    ]);
+}
+
+#[test]
+fn test_skip_temporary_errors() {
+    let mut core = Core::new().unwrap();
+    let sub = sequence(vec![
+            Err(temporary()),
+            Ok("127.0.0.1:80"),
+            Err(temporary()),
+            Ok("127.0.0.2:80"),
+        ]).interval_subscriber(Duration::from_millis(10));
+    let values = core.run(
+        sub.subscribe(&"localhost".parse().unwrap()).take(2).collect()
+    ).unwrap();
+    assert_eq!(values, vec![
+        Address::parse_list(&["127.0.0.1:80"]).unwrap(),
+        Address::parse_list(&["127.0.0.2:80"]).unwrap(),
+    ]);
+}
+
+#[test]
+#[should_panic(expected="NameNotFound")]
+fn test_permanent_error() {
+    let mut core = Core::new().unwrap();
+    let sub = sequence(vec![
+            Ok("127.0.0.1:80"),
+            Err(Error::NameNotFound),
+        ]).interval_subscriber(Duration::from_millis(10));
+    core.run(
+        sub.subscribe(&"localhost".parse().unwrap()).collect()
+    ).unwrap();
+}
+
+#[test]
+fn test_zero_period() {
+    let mut core = Core::new().unwrap();
+    let sub = sequence(vec![Ok("127.0.0.1:80")])
+        .interval_subscriber(Duration::new(0, 0));
+    let stream = sub.subscribe(&"localhost".parse().unwrap());
+    // value never changes, so stream must yield to other futures
+    let timeout = Delay::new(Instant::now() + Duration::from_millis(50));
+    match core.run(stream.take(2).collect().select2(timeout)) {
+        Ok(Either::B(..)) => {}
+        _ => panic!("stream is finished before timeout"),
+    }
+}
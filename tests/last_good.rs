@@ -0,0 +1,71 @@
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use abstract_ns::{Subscribe, Name, Address};
+use abstract_ns::combinators::LastKnownGood;
+use abstract_ns::static_resolver::StaticResolver;
+#[cfg(feature="testing")] use abstract_ns::{HostSubscribe, IpList};
+#[cfg(feature="testing")] use abstract_ns::testing::MockResolver;
+
+
+fn name(x: &str) -> Name {
+    x.parse().unwrap()
+}
+
+#[test]
+fn resubscribe_on_error() {
+    let mut core = Core::new().unwrap();
+    let res = StaticResolver::new();
+    let a = Address::parse_list(&["127.0.0.1:80"]).unwrap();
+    let b = Address::parse_list(&["127.0.0.2:80"]).unwrap();
+    res.insert_address(&name("example.org"), a.clone());
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let mut lkg = LastKnownGood::new(res.clone());
+    lkg.resubscribe_delay(Duration::from_millis(10));
+    lkg.on_error({
+        let errors = errors.clone();
+        move |name, e| errors.lock().unwrap()
+            .push(format!("{}: {}", name, e))
+    });
+    let handle = core.handle();
+    let update = Timeout::new(Duration::from_millis(20), &handle).unwrap()
+        .and_then({
+            let res = res.clone();
+            let handle = handle.clone();
+            move |()| {
+                res.remove_address(&name("example.org"));
+                Timeout::new(Duration::from_millis(30), &handle).unwrap()
+            }
+        })
+        .map({
+            let b = b.clone();
+            move |()| res.insert_address(&name("example.org"), b)
+        });
+    handle.spawn(update.map_err(|e| panic!("timer error: {}", e)));
+    let stream = lkg.subscribe(&name("example.org"));
+    let values = core.run(stream.take(2).collect()).unwrap();
+    assert_eq!(values, vec![a, b]);
+    let errors = errors.lock().unwrap();
+    assert!(!errors.is_empty());
+    assert_eq!(errors[0], "example.org: name not found");
+}
+
+#[test]
+#[cfg(feature="testing")]
+fn host_pass_through() {
+    let mut core = Core::new().unwrap();
+    let mock = MockResolver::new();
+    let ips = IpList::parse_list(&["127.0.0.1"]).unwrap();
+    mock.push_host(&name("example.org"), ips.clone());
+    let lkg = LastKnownGood::new(mock);
+    let stream = lkg.subscribe_host(&name("example.org"));
+    let (value, _) = core.run(stream.into_future())
+        .map_err(|(e, _)| e).unwrap();
+    assert_eq!(value, Some(ips));
+}
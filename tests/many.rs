@@ -0,0 +1,107 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use std::sync::{Arc, Mutex};
+
+use futures::Stream;
+use futures::sync::mpsc::unbounded;
+use abstract_ns::{Subscribe, Name, Address, Error};
+use abstract_ns::static_resolver::StaticResolver;
+
+
+fn name(x: &str) -> Name {
+    x.parse().unwrap()
+}
+
+fn addr(x: &[&str]) -> Address {
+    Address::parse_list(x).unwrap()
+}
+
+fn sorted(a: Address) -> Vec<String> {
+    let mut v = a.at(0).addresses().map(|x| x.to_string()).collect::<Vec<_>>();
+    v.sort();
+    v
+}
+
+#[test]
+fn merge_and_update() {
+    let res = StaticResolver::new();
+    res.insert_address(&name("a.example.org"), addr(&["127.0.0.1:80"]));
+    res.insert_address(&name("b.example.org"), addr(&["127.0.0.2:80"]));
+    res.insert_address(&name("c.example.org"), addr(&["127.0.0.3:80"]));
+    let (tx, rx) = unbounded();
+    let names = rx.map_err(|()| -> Error { unreachable!() });
+    let mut stream = res.clone().subscribe_many(names).wait();
+
+    tx.unbounded_send(vec![name("a.example.org"), name("b.example.org")])
+        .unwrap();
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.1:80", "127.0.0.2:80"]);
+
+    res.insert_address(&name("b.example.org"), addr(&["127.0.0.4:80"]));
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.1:80", "127.0.0.4:80"]);
+
+    tx.unbounded_send(vec![name("c.example.org"), name("b.example.org")])
+        .unwrap();
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.3:80", "127.0.0.4:80"]);
+
+    // a is not subscribed anymore, so changing it yields nothing,
+    // next value is the change of c
+    res.insert_address(&name("a.example.org"), addr(&["127.0.0.5:80"]));
+    res.insert_address(&name("c.example.org"), addr(&["127.0.0.6:80"]));
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.4:80", "127.0.0.6:80"]);
+}
+
+#[test]
+fn failed_member() {
+    let res = StaticResolver::new();
+    res.insert_address(&name("a.example.org"), addr(&["127.0.0.1:80"]));
+    let (tx, rx) = unbounded();
+    let names = rx.map_err(|()| -> Error { unreachable!() });
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let mut stream = res.clone().subscribe_many(names);
+    stream.on_error({
+        let errors = errors.clone();
+        move |name, e| errors.lock().unwrap()
+            .push(format!("{}: {}", name, e))
+    });
+    let mut stream = stream.wait();
+
+    tx.unbounded_send(vec![name("a.example.org"), name("b.example.org")])
+        .unwrap();
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.1:80"]);
+    assert_eq!(*errors.lock().unwrap(),
+               vec!["b.example.org: name not found"]);
+
+    // failed name is subscribed again on the next list
+    res.insert_address(&name("b.example.org"), addr(&["127.0.0.2:80"]));
+    tx.unbounded_send(vec![name("a.example.org"), name("b.example.org")])
+        .unwrap();
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.1:80", "127.0.0.2:80"]);
+
+    res.remove_address(&name("a.example.org"));
+    assert_eq!(sorted(stream.next().unwrap().unwrap()),
+               vec!["127.0.0.2:80"]);
+}
+
+#[test]
+fn keep_priorities() {
+    let res = StaticResolver::new();
+    res.insert_address(&name("a.example.org"),
+        "[127.0.0.1:80 w=1] / [127.0.0.2:80]".parse().unwrap());
+    res.insert_address(&name("b.example.org"),
+        "[127.0.0.1:80 w=2, 127.0.0.3:80 w=3]".parse().unwrap());
+    let (tx, rx) = unbounded();
+    let names = rx.map_err(|()| -> Error { unreachable!() });
+    let mut stream = res.clone().subscribe_many(names).wait();
+    tx.unbounded_send(vec![name("a.example.org"), name("b.example.org")])
+        .unwrap();
+    assert_eq!(stream.next().unwrap().unwrap(),
+        "[127.0.0.1:80 w=3, 127.0.0.3:80 w=3] / [127.0.0.2:80]"
+        .parse().unwrap());
+}
@@ -123,3 +123,53 @@ fn test_traits() {
     all_traits(HostMock.null_service_resolver().frozen_subscriber());
     all_traits(SvcMock.null_host_resolver().frozen_subscriber());
 }
+
+#[test]
+fn test_default_port() {
+    assert_eq!(
+        HostMock.with_default_port(80)
+        .resolve(&"localhost".parse().unwrap()).wait().unwrap(),
+        Address::parse_list(&["127.0.0.2:80"]).unwrap()
+    );
+    assert_eq!(
+        HostMock.frozen_host_subscriber().with_default_port(80)
+        .subscribe(&"localhost".parse().unwrap())
+            .wait().next().unwrap().unwrap(),
+        Address::parse_list(&["127.0.0.2:80"]).unwrap()
+    );
+    all_traits(HostMock.frozen_host_subscriber().with_default_port(80));
+}
+
+#[test]
+fn test_boxed() {
+    let resolvers = [
+        Mock.boxed_resolver(),
+        SvcMock.boxed_resolver(),
+        HostMock.with_default_port(443).boxed_resolver(),
+    ];
+    let name = "localhost".parse().unwrap();
+    assert_eq!(
+        resolvers.iter().map(|r| r.resolve(&name).wait().unwrap())
+            .collect::<Vec<_>>(),
+        vec![
+            Address::parse_list(&["127.0.0.1:443"]).unwrap(),
+            Address::parse_list(&["127.0.0.2:443"]).unwrap(),
+            Address::parse_list(&["127.0.0.2:443"]).unwrap(),
+        ]);
+    let hosts = [
+        Mock.boxed_host_resolver(),
+        HostMock.boxed_host_resolver(),
+    ];
+    assert_eq!(hosts[1].resolve_host(&name).wait().unwrap(),
+        IpList::parse_list(&["127.0.0.2"]).unwrap());
+    assert_eq!(
+        Mock.frozen_subscriber().boxed_subscriber()
+        .subscribe(&name).wait().next().unwrap().unwrap(),
+        Address::parse_list(&["127.0.0.1:443"]).unwrap()
+    );
+    assert_eq!(
+        HostMock.frozen_host_subscriber().boxed_host_subscriber()
+        .subscribe_host(&name).wait().next().unwrap().unwrap(),
+        IpList::parse_list(&["127.0.0.2"]).unwrap()
+    );
+}
@@ -0,0 +1,100 @@
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::Stream;
+use futures::future::{FutureResult, ok, err};
+use futures::stream::{iter_result, IterResult};
+use tokio_core::reactor::Core;
+use abstract_ns::{HostResolve, HostSubscribe, Name, IpList, Error};
+use abstract_ns::combinators::Retry;
+
+
+/// Fails with temporary error the specified number of times
+#[derive(Debug)]
+struct Flaky {
+    failures: Mutex<u32>,
+}
+
+fn flaky(failures: u32) -> Retry<Flaky> {
+    let mut retry = Retry::new(Flaky { failures: Mutex::new(failures) });
+    retry.initial_delay(Duration::from_millis(1))
+        .max_delay(Duration::from_millis(10))
+        .max_attempts(Some(3));
+    retry
+}
+
+fn temporary() -> Error {
+    Error::TemporaryError(Box::new(
+        io::Error::new(io::ErrorKind::ConnectionRefused, "oh no!")))
+}
+
+impl Flaky {
+    fn fail(&self) -> bool {
+        let mut failures = self.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl HostResolve for Flaky {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, _name: &Name) -> Self::HostFuture {
+        if self.fail() {
+            err(temporary())
+        } else {
+            ok(IpList::parse_list(&["127.0.0.1"]).unwrap())
+        }
+    }
+}
+
+impl HostSubscribe for Flaky {
+    type HostStream = IterResult<std::vec::IntoIter<Result<IpList, Error>>>;
+    type HostError = Error;
+    fn subscribe_host(&self, _name: &Name) -> Self::HostStream {
+        let ips = IpList::parse_list(&["127.0.0.1"]).unwrap();
+        if self.fail() {
+            iter_result(vec![Ok(ips), Err(temporary())])
+        } else {
+            iter_result(vec![Ok(ips)])
+        }
+    }
+}
+
+#[test]
+fn test_retry() {
+    let mut core = Core::new().unwrap();
+    let r = flaky(2);
+    assert_eq!(
+        core.run(r.resolve_host(&"localhost".parse().unwrap())).unwrap(),
+        IpList::parse_list(&["127.0.0.1"]).unwrap());
+}
+
+#[test]
+fn test_too_many_attempts() {
+    let mut core = Core::new().unwrap();
+    let r = flaky(3);
+    match core.run(r.resolve_host(&"localhost".parse().unwrap())) {
+        Err(Error::TemporaryError(_)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_resubscribe() {
+    let mut core = Core::new().unwrap();
+    let r = flaky(5);
+    // every subscription yields a value, so attempts are reset
+    let values = core.run(
+        r.subscribe_host(&"localhost".parse().unwrap()).collect()
+    ).unwrap();
+    assert_eq!(values.len(), 6);
+}
@@ -0,0 +1,38 @@
+#![cfg(feature="serde")]
+extern crate abstract_ns;
+extern crate serde_json;
+
+use abstract_ns::{Name, Address, IpList};
+use abstract_ns::addr::Builder;
+
+
+#[test]
+fn address() {
+    let mut builder = Builder::new();
+    builder.add_addresses(&[(10, "127.0.0.2:80".parse().unwrap()),
+                            (20, "127.0.0.1:80".parse().unwrap())]);
+    builder.add_addresses(&[(0, "[::1]:8080".parse().unwrap())]);
+    let addr = builder.into_address();
+    let json = serde_json::to_string(&addr).unwrap();
+    assert_eq!(json,
+        r#"[[[20,"127.0.0.1:80"],[10,"127.0.0.2:80"]],[[0,"[::1]:8080"]]]"#);
+    assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), addr);
+}
+
+#[test]
+fn ip_list() {
+    let ips = IpList::parse_list(&["127.0.0.1", "::1"]).unwrap();
+    let json = serde_json::to_string(&ips).unwrap();
+    assert_eq!(json, r#"["127.0.0.1","::1"]"#);
+    assert_eq!(serde_json::from_str::<IpList>(&json).unwrap(), ips);
+}
+
+#[test]
+fn name() {
+    let name: Name = "example.org".parse().unwrap();
+    let json = serde_json::to_string(&name).unwrap();
+    assert_eq!(json, r#""example.org""#);
+    assert_eq!(serde_json::from_str::<Name>(&json).unwrap(), name);
+    assert!(serde_json::from_str::<Name>(r#""example..org""#).is_err());
+    assert!(serde_json::from_str::<Name>(r#""-example.org""#).is_err());
+}
@@ -0,0 +1,97 @@
+extern crate abstract_ns;
+extern crate futures;
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use abstract_ns::{HostResolve, Resolve, Name, Address, IpList, Error};
+use abstract_ns::{Subscribe, HostSubscribe};
+use abstract_ns::static_resolver::StaticResolver;
+
+
+fn name(x: &str) -> Name {
+    x.parse().unwrap()
+}
+
+fn addr(x: &str) -> Address {
+    Address::parse_list(&[x]).unwrap()
+}
+
+fn ips(x: &str) -> IpList {
+    IpList::parse_list(&[x]).unwrap()
+}
+
+#[test]
+fn resolve() {
+    let res = StaticResolver::new();
+    res.insert_address(&name("example.org"), addr("127.0.0.1:80"));
+    res.insert_host(&name("example.org"), ips("127.0.0.2"));
+    assert_eq!(res.resolve(&name("example.org")).wait().unwrap(),
+               addr("127.0.0.1:80"));
+    assert_eq!(res.resolve_host(&name("example.org")).wait().unwrap(),
+               ips("127.0.0.2"));
+    match res.resolve(&name("example.com")).wait() {
+        Err(Error::NameNotFound) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+    res.remove_host(&name("example.org"));
+    match res.resolve_host(&name("example.org")).wait() {
+        Err(Error::NameNotFound) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+    assert!(res.resolve(&name("example.org")).wait().is_ok());
+}
+
+#[test]
+fn subscribe_updates() {
+    let res = StaticResolver::new();
+    let handle = res.clone();
+    handle.insert_address(&name("example.org"), addr("127.0.0.1:80"));
+    let mut updates = res.subscribe(&name("example.org")).wait();
+    assert_eq!(updates.next().unwrap().unwrap(), addr("127.0.0.1:80"));
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        // same value, must not be yielded
+        handle.insert_address(&name("example.org"), addr("127.0.0.1:80"));
+        handle.insert_address(&name("example.org"), addr("127.0.0.2:80"));
+        thread::sleep(Duration::from_millis(50));
+        handle.remove_address(&name("example.org"));
+    });
+    assert_eq!(updates.next().unwrap().unwrap(), addr("127.0.0.2:80"));
+    match updates.next() {
+        Some(Err(Error::NameNotFound)) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+}
+
+#[test]
+fn subscribe_replace() {
+    let res = StaticResolver::new();
+    res.insert_host(&name("a.example.org"), ips("127.0.0.1"));
+    res.insert_host(&name("b.example.org"), ips("127.0.0.2"));
+    let mut a = res.subscribe_host(&name("a.example.org")).wait();
+    let mut b = res.subscribe_host(&name("b.example.org")).wait();
+    assert_eq!(a.next().unwrap().unwrap(), ips("127.0.0.1"));
+    assert_eq!(b.next().unwrap().unwrap(), ips("127.0.0.2"));
+    let mut hosts = HashMap::new();
+    hosts.insert(name("a.example.org"), ips("127.0.0.1"));
+    hosts.insert(name("b.example.org"), ips("127.0.0.3"));
+    res.replace(HashMap::new(), hosts);
+    assert_eq!(b.next().unwrap().unwrap(), ips("127.0.0.3"));
+    res.replace(HashMap::new(), HashMap::new());
+    match a.next() {
+        Some(Err(Error::NameNotFound)) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+}
+
+#[test]
+fn subscribe_missing() {
+    let res = StaticResolver::new();
+    match res.subscribe(&name("example.org")).wait().next() {
+        Some(Err(Error::NameNotFound)) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+}
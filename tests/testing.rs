@@ -0,0 +1,113 @@
+#![cfg(feature="testing")]
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream};
+use tokio_core::reactor::Core;
+use abstract_ns::{HostResolve, Resolve, Name, Address, IpList, Error};
+use abstract_ns::{Subscribe, HostSubscribe};
+use abstract_ns::testing::{MockResolver, Reply, Call};
+
+
+fn name(x: &str) -> Name {
+    x.parse().unwrap()
+}
+
+fn addr(x: &str) -> Address {
+    Address::parse_list(&[x]).unwrap()
+}
+
+fn ips(x: &str) -> IpList {
+    IpList::parse_list(&[x]).unwrap()
+}
+
+#[test]
+fn programmed_replies() {
+    let mock = MockResolver::new();
+    mock.set_address(&name("a.example.org"), addr("127.0.0.1:80"));
+    mock.set_host(&name("a.example.org"), ips("127.0.0.2"));
+    mock.set_address(&name("b.example.org"),
+                     Reply::TemporaryError("oh no!".into()));
+    assert_eq!(mock.resolve(&name("a.example.org")).wait().unwrap(),
+               addr("127.0.0.1:80"));
+    assert_eq!(mock.resolve(&name("a.example.org")).wait().unwrap(),
+               addr("127.0.0.1:80"));
+    assert_eq!(mock.resolve_host(&name("a.example.org")).wait().unwrap(),
+               ips("127.0.0.2"));
+    match mock.resolve(&name("b.example.org")).wait() {
+        Err(Error::TemporaryError(ref e)) if e.to_string() == "oh no!" => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    match mock.resolve_host(&name("b.example.org")).wait() {
+        Err(Error::NameNotFound) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert_eq!(mock.calls(), vec![
+        Call::Resolve(name("a.example.org")),
+        Call::Resolve(name("a.example.org")),
+        Call::ResolveHost(name("a.example.org")),
+        Call::Resolve(name("b.example.org")),
+        Call::ResolveHost(name("b.example.org")),
+    ]);
+    mock.clear_calls();
+    assert_eq!(mock.calls(), vec![]);
+}
+
+#[test]
+fn delayed_reply() {
+    let mut core = Core::new().unwrap();
+    let mock = MockResolver::new();
+    mock.set_address(&name("example.org"),
+        Reply::Value(addr("127.0.0.1:80"))
+        .delayed(Duration::from_millis(50)));
+    let start = Instant::now();
+    assert_eq!(core.run(mock.resolve(&name("example.org"))).unwrap(),
+               addr("127.0.0.1:80"));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn scripted_updates() {
+    let mock = MockResolver::new();
+    mock.push_host(&name("example.org"), ips("127.0.0.1"));
+    mock.push_host(&name("example.org"), ips("127.0.0.2"));
+    let mut updates = mock.subscribe_host(&name("example.org")).wait();
+    assert_eq!(updates.next().unwrap().unwrap(), ips("127.0.0.1"));
+    assert_eq!(updates.next().unwrap().unwrap(), ips("127.0.0.2"));
+    mock.push_host(&name("example.org"), ips("127.0.0.3"));
+    mock.push_host(&name("example.org"), Reply::NameNotFound);
+    assert_eq!(updates.next().unwrap().unwrap(), ips("127.0.0.3"));
+    match updates.next() {
+        Some(Err(Error::NameNotFound)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    // every subscriber plays the script from the start
+    let mut updates = mock.subscribe_host(&name("example.org")).wait();
+    assert_eq!(updates.next().unwrap().unwrap(), ips("127.0.0.1"));
+    assert_eq!(mock.calls(), vec![
+        Call::SubscribeHost(name("example.org")),
+        Call::SubscribeHost(name("example.org")),
+    ]);
+}
+
+#[test]
+fn push_to_active_subscriber() {
+    let mut core = Core::new().unwrap();
+    let mock = MockResolver::new();
+    let stream = mock.subscribe(&name("example.org"));
+    let handle = mock.clone();
+    core.handle().spawn(tokio_core::reactor::Timeout::new(
+            Duration::from_millis(10), &core.handle()).unwrap()
+        .map(move |()| {
+            handle.push_address(&name("example.org"), addr("127.0.0.1:80"));
+            handle.push_address(&name("example.org"),
+                Reply::Value(addr("127.0.0.2:80"))
+                .delayed(Duration::from_millis(10)));
+        })
+        .map_err(|e| panic!("timer error: {}", e)));
+    let values = core.run(stream.take(2).collect()).unwrap();
+    assert_eq!(values, vec![addr("127.0.0.1:80"), addr("127.0.0.2:80")]);
+}
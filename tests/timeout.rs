@@ -0,0 +1,90 @@
+extern crate abstract_ns;
+extern crate futures;
+extern crate tokio_core;
+
+use std::time::Duration;
+
+use futures::{Future, Stream};
+use futures::future::{Empty, FutureResult, empty, ok};
+use tokio_core::reactor::Core;
+use abstract_ns::{HostResolve, Resolve, Subscribe, Name, Address, IpList};
+use abstract_ns::Error;
+use abstract_ns::combinators::Timeout;
+
+
+#[derive(Debug)]
+struct Hanging;
+
+impl Resolve for Hanging {
+    type Future = Empty<Address, Error>;
+    fn resolve(&self, _name: &Name) -> Self::Future {
+        empty()
+    }
+}
+
+impl HostResolve for Hanging {
+    type HostFuture = FutureResult<IpList, Error>;
+    fn resolve_host(&self, _name: &Name) -> Self::HostFuture {
+        ok(IpList::parse_list(&["127.0.0.1"]).unwrap())
+    }
+}
+
+impl Subscribe for Hanging {
+    type Stream = Box<dyn Stream<Item=Address, Error=Error>>;
+    type Error = Error;
+    fn subscribe(&self, name: &Name) -> Self::Stream {
+        let hanging = empty().into_stream();
+        if name.as_ref() == "hanging" {
+            Box::new(hanging)
+        } else {
+            let addr = Address::parse_list(&["127.0.0.1:80"]).unwrap();
+            Box::new(ok(addr).into_stream().chain(hanging))
+        }
+    }
+}
+
+#[test]
+fn test_timeout() {
+    let mut core = Core::new().unwrap();
+    let r = Timeout::new(Hanging, Duration::from_millis(10));
+    match core.run(r.resolve(&"localhost".parse().unwrap())) {
+        Err(Error::Timeout) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_no_timeout() {
+    let mut core = Core::new().unwrap();
+    let r = Timeout::new(Hanging, Duration::from_millis(10));
+    assert_eq!(
+        core.run(r.resolve_host(&"localhost".parse().unwrap())).unwrap(),
+        IpList::parse_list(&["127.0.0.1"]).unwrap());
+}
+
+#[test]
+fn test_first_value_timeout() {
+    let mut core = Core::new().unwrap();
+    let r = Timeout::new(Hanging, Duration::from_millis(10));
+    match core.run(r.subscribe(&"hanging".parse().unwrap()).into_future()) {
+        Err((Error::Timeout, _)) => {}
+        Err((e, _)) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("unexpected value"),
+    }
+}
+
+#[test]
+fn test_no_timeout_after_first_value() {
+    let mut core = Core::new().unwrap();
+    let r = Timeout::new(Hanging, Duration::from_millis(10));
+    let deadline = tokio_core::reactor::Timeout::new(
+        Duration::from_millis(50), &core.handle()).unwrap();
+    let stream = r.subscribe(&"localhost".parse().unwrap())
+        .map(Some).map_err(Error::into_io)
+        .select(deadline.into_stream().map(|()| None));
+    let values = core.run(stream.take(2).collect()).unwrap();
+    assert_eq!(values, vec![
+        Some(Address::parse_list(&["127.0.0.1:80"]).unwrap()),
+        None,
+    ]);
+}